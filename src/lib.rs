@@ -1,10 +1,19 @@
+// anchor 0.31's `#[program]` expansion and the system ix helpers still go
+// through APIs deprecated in solana-program 2.x
+#![allow(deprecated)]
+
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::{hash, hashv};
+use anchor_lang::solana_program::incinerator;
+use anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
 use anchor_lang::solana_program::rent::Rent;
 use anchor_lang::solana_program::{
     program::{invoke, invoke_signed},
     system_instruction,
 };
 use anchor_lang::solana_program::system_program;
+use anchor_lang::solana_program::sysvar::instructions as sysvar_instructions;
+use anchor_spl::token::{self, TokenAccount};
 
 #[cfg(not(feature = "no-entrypoint"))]
 use solana_security_txt::security_txt;
@@ -18,6 +27,14 @@ security_txt! {
     source_code: "https://github.com/tycoonscripts/jigsaw"
 }
 
+// the same values for `security_info`; `security_txt!` only takes literals,
+// so these have to be kept in step with it by hand
+pub const SECURITY_NAME: &str = "Treasury Escrow";
+pub const SECURITY_PROJECT_URL: &str = "https://jigsaw.chat";
+pub const SECURITY_CONTACTS: &str = "email:security@jigsaw.chat";
+pub const SECURITY_POLICY: &str = "https://jigsaw.chat/security-policy";
+pub const SECURITY_SOURCE_CODE: &str = "https://github.com/tycoonscripts/jigsaw";
+
 // Temporary placeholder Program ID for building. Replace with your deployed program ID.
 declare_id!("6GabEnTZtPMyUDkrbzEMDktDupZ3gxVWb6oEHBsoRZ61");
 
@@ -31,555 +48,6297 @@ pub mod treasury_escrow {
         fee_cap: u64,
         marketing_bps: u16,
     ) -> Result<()> {
-        let escrow = &mut ctx.accounts.escrow;
-    
-        // -------------------------------------------------
-        // 1. Create the vault PDA account manually
-        // -------------------------------------------------
-    
-        // how much rent-exempt lamports for an account with 0 data bytes
-        let rent_lamports = Rent::get()?.minimum_balance(0);
-    
-        // bump for vault PDA
-        let vault_bump = ctx.bumps.escrow_vault;
-    
-        // seeds we will sign with for the new account
-        let escrow_seed: &[u8] = b"escrow";
-        let vault_seed: &[u8] = b"vault";
-        let bump_seed: &[u8] = &[vault_bump];
-        let signer_seeds: &[&[u8]] = &[escrow_seed, vault_seed, bump_seed];
-    
-        // build the `create_account` ix:
-        // - `authority` funds it
-        // - new account is `escrow_vault`
-        // - owner is this program (crate::ID)
-        invoke_signed(
-            &system_instruction::create_account(
-                &ctx.accounts.authority.key(),               // from
-                &ctx.accounts.escrow_vault.key(),            // new account pubkey (the PDA)
-                rent_lamports,                               // lamports
-                0,                                           // space in bytes
-                &system_program::ID,                                  // owner = this program
-            ),
-            &[
-                ctx.accounts.authority.to_account_info(),
-                ctx.accounts.escrow_vault.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
-            &[signer_seeds],
-        )?;
-    
-        // -------------------------------------------------
-        // 2. Initialize escrow state
-        // -------------------------------------------------
-        escrow.authority = ctx.accounts.authority.key();
-        escrow.base_fee = base_fee;
-        escrow.fee_cap = fee_cap;
-        escrow.current_fee = base_fee;
-        escrow.marketing_wallet = ctx.accounts.marketing_wallet.key();
-        escrow.marketing_bps = marketing_bps;
-        escrow.messages_count = 0;
-        escrow.last_sender = Pubkey::default();
-        escrow.timer_active = false;
-        escrow.deadline = 0;
-        escrow.ended = false;
-        escrow.bump = ctx.bumps.escrow;
-    
+        let recipient = MarketingSplit {
+            wallet: ctx.accounts.marketing_wallet.key(),
+            bps: marketing_bps,
+        };
+        init_escrow(ctx, base_fee, fee_cap, marketing_bps, &[recipient])
+    }
+
+    // Thin wrapper over `initialize` taking whole SOL; state stays in lamports.
+    pub fn initialize_sol(
+        ctx: Context<Initialize>,
+        base_fee_sol: u64,
+        fee_cap_sol: u64,
+        marketing_bps: u16,
+    ) -> Result<()> {
+        initialize(
+            ctx,
+            sol_to_lamports(base_fee_sol)?,
+            sol_to_lamports(fee_cap_sol)?,
+            marketing_bps,
+        )
+    }
+
+    // `initialize` plus the full marketing splitter in one call, so a game is
+    // never live with only its primary recipient configured
+    pub fn initialize_with_splits(
+        ctx: Context<Initialize>,
+        base_fee: u64,
+        fee_cap: u64,
+        recipients: Vec<MarketingSplit>,
+    ) -> Result<()> {
+        let total_bps = validate_marketing_splits(&recipients)?;
+        require_keys_eq!(
+            ctx.accounts.marketing_wallet.key(),
+            recipients[0].wallet,
+            ErrorCode::BadParams
+        );
+
+        init_escrow(ctx, base_fee, fee_cap, total_bps, &recipients)?;
+
+        for recipient in &recipients {
+            emit!(MarketingParamsUpdated {
+                wallet: recipient.wallet,
+                bps: recipient.bps,
+            });
+        }
+
         Ok(())
     }
 
-    pub fn submit_message(ctx: Context<SubmitMessage>, msg_hash: [u8; 32]) -> Result<()> {
-        let escrow = &mut ctx.accounts.escrow;
+    // `client_nonce` makes wallet retries safe: if it equals the nonce of the
+    // payer's last accepted submission, the call is a no-op success (no fee,
+    // no state change) and emits `DuplicateSubmitIgnored`. Only the most
+    // recent nonce is remembered, so a retry only dedupes until the payer's
+    // next successful submission with a different nonce. `None` opts out.
+    pub fn submit_message<'info>(
+        ctx: Context<'_, '_, '_, 'info, SubmitMessage<'info>>,
+        msg_hash: [u8; 32],
+        client_nonce: Option<u64>,
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
         let clock = Clock::get()?;
-        let fee_paid = escrow.current_fee;
-    
-        // 1. game still live?
-        require!(!escrow.ended, ErrorCode::GameEnded);
+        let fee_paid = escrow.fee_due(clock.unix_timestamp);
+
+        // 1-2. game still live and timer not expired
+        let reason = ctx
+            .accounts
+            .submission_status(clock.unix_timestamp, false, client_nonce)?;
+        if reason == submit_reason::DUPLICATE_SUBMIT {
+            emit!(DuplicateSubmitIgnored {
+                payer: ctx.accounts.payer.key(),
+                client_nonce: client_nonce.unwrap(),
+            });
+            return Ok(());
+        }
+        require!(reason == submit_reason::ELIGIBLE, submit_error(reason));
         require_keys_eq!(
             ctx.accounts.marketing_wallet.key(),
             escrow.marketing_wallet,
             ErrorCode::Unauthorized
         );
     
-        // 2. timer not expired if active
-        if escrow.timer_active {
-            require!(
-                clock.unix_timestamp <= escrow.deadline,
-                ErrorCode::TimerExpired
-            );
-        }
-    
-        // 3. sanity: payer can afford the fee
-        let payer_lamports = ctx.accounts.payer.lamports();
-        require!(payer_lamports >= escrow.current_fee, ErrorCode::InsufficientFee);
-    
-        // -------------------------------------------------
-        // 4. compute splits
-        // -------------------------------------------------
-        // marketing_fee = current_fee * bps / 10000
-        let marketing_fee: u64 = (escrow.current_fee as u128)
-            .checked_mul(escrow.marketing_bps as u128)
-            .unwrap()
-            .checked_div(10_000)
-            .unwrap() as u64;
-    
-        // prize portion is whatever's left after marketing skim
-        let prize_fee: u64 = escrow
-            .current_fee
-            .checked_sub(marketing_fee)
-            .unwrap();
-    
-        // -------------------------------------------------
-        // 5. payer -> escrow_vault (the prize pool)
-        // -------------------------------------------------
-        if prize_fee > 0 {
-            invoke(
-                &system_instruction::transfer(
-                    &ctx.accounts.payer.key(),
-                    &ctx.accounts.escrow_vault.key(),
-                    prize_fee,
-                ),
-                &[
-                    ctx.accounts.payer.to_account_info(),
-                    ctx.accounts.escrow_vault.to_account_info(),
-                    ctx.accounts.system_program.to_account_info(),
-                ],
-            )?;
-        }
-    
-        // -------------------------------------------------
-        // 6. payer -> marketing_wallet (the rake)
-        // -------------------------------------------------
-        if marketing_fee > 0 && escrow.marketing_wallet != Pubkey::default() {
-            invoke(
-                &system_instruction::transfer(
-                    &ctx.accounts.payer.key(),
-                    &ctx.accounts.marketing_wallet.key(),
-                    marketing_fee,
-                ),
-                &[
-                    ctx.accounts.payer.to_account_info(),
-                    ctx.accounts.marketing_wallet.to_account_info(), // <-- add this
-                    ctx.accounts.system_program.to_account_info(),
-                ],
-            )?;
-            emit!(MarketingFeeSent { wallet: ctx.accounts.marketing_wallet.key(), amount: marketing_fee });
-        }        
+        // 3. sanity: payer can afford the fee (plus any self-submit penalty)
+        let penalty = escrow.self_submit_penalty(
+            &ctx.accounts.participant,
+            fee_paid,
+            clock.unix_timestamp,
+        );
+        let charge = fee_paid.checked_add(penalty).unwrap();
+        let reason = escrow.fee_status(fee_paid, charge, ctx.accounts.payer.lamports());
+        require!(reason == submit_reason::ELIGIBLE, submit_error(reason));
     
         // -------------------------------------------------
-        // 7. update on-chain state
+        // 4. compute splits; the penalty is prize-only
         // -------------------------------------------------
-        escrow.messages_count = escrow.messages_count.checked_add(1).unwrap();
-        escrow.last_sender = ctx.accounts.payer.key();
-    
-        // timer rules
-        const START_AFTER: u64 = 10;
-        const EXTEND_SECONDS: i64 = 3600;
-    
-        let mut timer_started = false;
-        let mut timer_extended = false;
-    
-        if !escrow.timer_active && escrow.messages_count >= START_AFTER {
-            escrow.timer_active = true;
-            escrow.deadline = clock.unix_timestamp.checked_add(EXTEND_SECONDS).unwrap();
-            timer_started = true;
-        } else if escrow.timer_active && clock.unix_timestamp <= escrow.deadline {
-            escrow.deadline = clock.unix_timestamp.checked_add(EXTEND_SECONDS).unwrap();
-            timer_extended = true;
+        let (marketing_fee, prize_fee) = escrow.split_fee(fee_paid);
+        escrow.emit_marketing_waived(fee_paid);
+        escrow.emit_marketing_floor(fee_paid);
+        escrow.emit_hold_reward(clock.unix_timestamp);
+        let prize_fee = prize_fee.checked_add(penalty).unwrap();
+        if penalty > 0 {
+            emit!(SelfSubmitPenalty { amount: penalty });
         }
     
         // -------------------------------------------------
-        // 8. bump the dynamic fee, capped
+        // 5-6. payer -> escrow_vault / marketing_wallet
         // -------------------------------------------------
-        let next_fee = (escrow.current_fee as u128)
-            .checked_mul(10078)
-            .unwrap()
-            .checked_div(10000)
-            .unwrap() as u64;
+        let prize_fee = ctx
+            .accounts
+            .transfer_fees(prize_fee, marketing_fee, ctx.remaining_accounts, None)?;
     
-        escrow.current_fee = if next_fee > escrow.fee_cap {
-            escrow.fee_cap
-        } else {
-            next_fee
-        };
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.add_marketing_paid(marketing_fee);
+        escrow.last_known_vault_balance = ctx.accounts.escrow_vault.lamports();
+        escrow.accounted_balance = escrow.accounted_balance.checked_add(prize_fee).unwrap();
     
         // -------------------------------------------------
-        // 9. emit events
+        // 7-9. update state, timer, fee and emit events
         // -------------------------------------------------
-        emit!(MessageSubmitted {
-            sender: ctx.accounts.payer.key(),
+        join_game(
+            escrow,
+            &mut ctx.accounts.participant,
+            ctx.accounts.payer.key(),
+            ctx.bumps.participant,
+            clock.unix_timestamp,
+        );
+        record_submission(
+            escrow,
+            ctx.accounts.payer.key(),
             msg_hash,
             fee_paid,
-            new_fee: escrow.current_fee,
-            timestamp: clock.unix_timestamp,
-        });
-    
-        if timer_started {
-            emit!(TimerStarted {
-                deadline: escrow.deadline,
-            });
-        } else if timer_extended {
-            emit!(TimerExtended {
-                new_deadline: escrow.deadline,
-            });
+            marketing_fee,
+            prize_fee,
+            clock.unix_timestamp,
+        )?;
+
+        let participant = &mut ctx.accounts.participant;
+        track_participant(
+            escrow,
+            participant,
+            ctx.accounts.payer.key(),
+            ctx.bumps.participant,
+            1,
+            fee_paid + penalty,
+            clock.unix_timestamp,
+        );
+        if client_nonce.is_some() {
+            participant.last_client_nonce = client_nonce;
         }
     
         Ok(())
     }    
 
-    pub fn claim_prize(ctx: Context<ClaimPrize>) -> Result<()> {
+    // Several submissions from one payer in a single instruction. Each hash pays
+    // the fee in effect at its turn (compounding as it goes), but the prize and
+    // marketing legs are transferred once for the cumulative totals. The last
+    // hash is the one that leaves the payer as `last_sender`.
+    pub fn submit_messages_batch<'info>(
+        ctx: Context<'_, '_, '_, 'info, SubmitMessage<'info>>,
+        hashes: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+
+        require!(
+            !hashes.is_empty() && hashes.len() <= MAX_BATCH_SUBMISSIONS,
+            ErrorCode::BadParams
+        );
+        ctx.accounts.require_accepting(clock.unix_timestamp, false)?;
+        require_keys_eq!(
+            ctx.accounts.marketing_wallet.key(),
+            ctx.accounts.escrow.marketing_wallet,
+            ErrorCode::Unauthorized
+        );
+
+        let payer = ctx.accounts.payer.key();
+        let escrow = &mut ctx.accounts.escrow;
+        let count = hashes.len() as u64;
+
+        // the first hash's penalty looks at the payer's previous play, so take
+        // it before `join_game` moves them into this game
+        let first_fee = escrow.fee_due(clock.unix_timestamp);
+        let first_penalty = escrow.self_submit_penalty(
+            &ctx.accounts.participant,
+            first_fee,
+            clock.unix_timestamp,
+        );
+        join_game(
+            escrow,
+            &mut ctx.accounts.participant,
+            payer,
+            ctx.bumps.participant,
+            clock.unix_timestamp,
+        );
+
+        let mut total_marketing: u64 = 0;
+        let mut total_prize: u64 = 0;
+        for (i, msg_hash) in hashes.into_iter().enumerate() {
+            // only the first hash can see a gap; the rest land in the same second
+            let fee_paid = escrow.fee_due(clock.unix_timestamp);
+            escrow.require_fee_in_bounds(fee_paid)?;
+            let (marketing_fee, prize_fee) = escrow.split_fee(fee_paid);
+            escrow.emit_marketing_waived(fee_paid);
+            escrow.emit_marketing_floor(fee_paid);
+            escrow.emit_hold_reward(clock.unix_timestamp);
+            // count it now so later hashes in the batch see the cap
+            escrow.add_marketing_paid(marketing_fee);
+
+            // every hash after the first is a resubmission within the same second
+            let penalty = if i == 0 {
+                first_penalty
+            } else if escrow.self_submit_cooldown > 0 {
+                escrow.self_submit_penalty_cut(fee_paid)
+            } else {
+                0
+            };
+            if penalty > 0 {
+                emit!(SelfSubmitPenalty { amount: penalty });
+            }
+
+            total_marketing = total_marketing.checked_add(marketing_fee).unwrap();
+            total_prize = total_prize
+                .checked_add(prize_fee)
+                .unwrap()
+                .checked_add(penalty)
+                .unwrap();
+
+            record_submission(
+                escrow,
+                payer,
+                msg_hash,
+                fee_paid,
+                marketing_fee,
+                prize_fee.checked_add(penalty).unwrap(),
+                clock.unix_timestamp,
+            )?;
+        }
+
+        let total_fee = total_marketing.checked_add(total_prize).unwrap();
+        require!(
+            ctx.accounts.payer.lamports() >= total_fee,
+            ErrorCode::InsufficientFee
+        );
+
+        let credited = ctx
+            .accounts
+            .transfer_fees(total_prize, total_marketing, ctx.remaining_accounts, None)?;
         let escrow = &mut ctx.accounts.escrow;
+        // any `max_pot` excess comes off the end of the batch, i.e. the last
+        // hash's prize leg
+        let excess = total_prize - credited;
+        escrow.last_prize_fee = escrow.last_prize_fee.saturating_sub(excess);
+        let total_prize = credited;
+        escrow.last_known_vault_balance = ctx.accounts.escrow_vault.lamports();
+        escrow.accounted_balance = escrow.accounted_balance.checked_add(total_prize).unwrap();
+
+        track_participant(
+            &mut ctx.accounts.escrow,
+            &mut ctx.accounts.participant,
+            payer,
+            ctx.bumps.participant,
+            count,
+            total_fee,
+            clock.unix_timestamp,
+        );
+
+        Ok(())
+    }
+
+    // Same flow as `submit_message`, but the fee is `fee_usd_cents` converted to
+    // lamports at the oracle's SOL/USD price instead of `current_fee`. The
+    // price update account must be the first remaining account, followed by
+    // any extra marketing recipients. `max_fee` caps the converted amount so a
+    // price move between signing and landing can't overcharge the payer.
+    pub fn submit_message_usd<'info>(
+        ctx: Context<'_, '_, '_, 'info, SubmitMessage<'info>>,
+        msg_hash: [u8; 32],
+        max_fee: u64,
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
         let clock = Clock::get()?;
-    
-        require!(escrow.timer_active, ErrorCode::GameNotEnded);
-        require!(clock.unix_timestamp >= escrow.deadline, ErrorCode::GameNotEnded);
-        require!(escrow.last_sender != Pubkey::default(), ErrorCode::NoWinner);
-        require!(!escrow.ended, ErrorCode::AlreadyClaimed);
-        require!(ctx.accounts.winner.key() == escrow.last_sender, ErrorCode::NotTheWinner);
-    
-        escrow.ended = true;
-    
-        // How much is in the vault right now?
-        let balance = ctx.accounts.escrow_vault.lamports();
-    
-        // Transfer all lamports from vault PDA → winner using invoke_signed
-        // (SystemProgram transfer signed by vault PDA seeds)
-        let bump = ctx.bumps.escrow_vault;
-    
-        let escrow_seed: &[u8] = b"escrow";
-        let vault_seed: &[u8] = b"vault";
-        let bump_seed: &[u8] = &[bump];
-    
-        let signer_seeds: &[&[u8]] = &[escrow_seed, vault_seed, bump_seed];
-    
-        invoke_signed(
-            &system_instruction::transfer(
-                &ctx.accounts.escrow_vault.key(),
-                &ctx.accounts.winner.key(),
-                balance,
-            ),
-            &[
-                ctx.accounts.escrow_vault.to_account_info(),
-                ctx.accounts.winner.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
-            &[signer_seeds],
-        )?;
-    
-        emit!(PrizeClaimed {
-            winner: ctx.accounts.winner.key(),
-            amount: balance,
+
+        ctx.accounts.require_accepting(clock.unix_timestamp, false)?;
+        require!(escrow.fee_usd_cents > 0, ErrorCode::OracleMismatch);
+
+        let (price_update, extra_recipients) = ctx
+            .remaining_accounts
+            .split_first()
+            .ok_or(ErrorCode::OracleMismatch)?;
+        require_keys_eq!(price_update.key(), escrow.oracle, ErrorCode::OracleMismatch);
+
+        let price = read_oracle_price(price_update, clock.unix_timestamp)?;
+        let fee_paid = usd_cents_to_lamports(escrow.fee_usd_cents, &price)?;
+        require!(fee_paid <= max_fee, ErrorCode::InsufficientFee);
+        require!(
+            ctx.accounts.payer.lamports() >= fee_paid,
+            ErrorCode::InsufficientFee
+        );
+
+        emit!(UsdFeeConverted {
+            fee_usd_cents: escrow.fee_usd_cents,
+            price: price.price,
+            exponent: price.exponent,
+            lamports: fee_paid,
         });
-    
-        Ok(())
+
+        let participant_bump = ctx.bumps.participant;
+        ctx.accounts.accept_fee(
+            msg_hash,
+            fee_paid,
+            extra_recipients,
+            participant_bump,
+            clock.unix_timestamp,
+            None,
+        )
     }
-    
 
-    pub fn jigsaw_approve_payout(ctx: Context<JigsawApprovePayout>) -> Result<()> {
-        let escrow = &mut ctx.accounts.escrow;
-    
-        // --- validity checks ---
-        require!(!escrow.ended, ErrorCode::AlreadyClaimed);
+    // `submit_message` for holders of an NFT from `required_collection`. The
+    // payer's token account and the mint's Metaplex metadata account come
+    // first in the remaining accounts, followed by any extra marketing
+    // recipients.
+    pub fn submit_message_nft_gated<'info>(
+        ctx: Context<'_, '_, '_, 'info, SubmitMessage<'info>>,
+        msg_hash: [u8; 32],
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        let clock = Clock::get()?;
+        let fee_paid = escrow.fee_due(clock.unix_timestamp);
+
+        ctx.accounts.require_accepting(clock.unix_timestamp, false)?;
+        escrow.emit_hold_reward(clock.unix_timestamp);
         require!(
-            ctx.accounts.winner.key() == escrow.last_sender,
-            ErrorCode::NotTheWinner
+            escrow.required_collection != Pubkey::default(),
+            ErrorCode::NftGateFailed
         );
-        require!(escrow.last_sender != Pubkey::default(), ErrorCode::NoWinner);
-    
-        // Mark game as ended so it can't be claimed twice
-        escrow.ended = true;
-    
-        // Read how many lamports are currently in the vault
-        let balance = ctx.accounts.escrow_vault.lamports();
-    
-        // Build signer seeds for the vault PDA
-        // vault PDA is seeds = [b"escrow", b"vault"], bump = ctx.bumps.escrow_vault
-        let bump = ctx.bumps.escrow_vault;
-    
-        let escrow_seed: &[u8] = b"escrow";
-        let vault_seed: &[u8] = b"vault";
-        let bump_seed: &[u8] = &[bump];
-    
-        let signer_seeds: &[&[u8]] = &[escrow_seed, vault_seed, bump_seed];
-    
-        // Transfer the entire vault balance to the winner using CPI
-        // The vault PDA signs this transfer via invoke_signed
-        invoke_signed(
+
+        let [token_account, metadata, extra_recipients @ ..] = ctx.remaining_accounts else {
+            return err!(ErrorCode::NftGateFailed);
+        };
+        require_nft_holder(
+            token_account,
+            metadata,
+            &ctx.accounts.payer.key(),
+            &escrow.required_collection,
+        )?;
+        require!(
+            ctx.accounts.payer.lamports() >= fee_paid,
+            ErrorCode::InsufficientFee
+        );
+
+        let participant_bump = ctx.bumps.participant;
+        ctx.accounts.accept_fee(
+            msg_hash,
+            fee_paid,
+            extra_recipients,
+            participant_bump,
+            clock.unix_timestamp,
+            None,
+        )
+    }
+
+    /// Accepts a message whose fee the payer transferred into `escrow_vault`
+    /// in the instruction just before this one, instead of transferring it
+    /// again.
+    ///
+    /// The credit is tied to that transfer, not to the vault balance alone.
+    /// Through the instructions sysvar (the first remaining account), the
+    /// previous instruction has to be a system-program transfer from `payer`
+    /// to `escrow_vault` covering `expected_fee` plus any self-submit
+    /// penalty. On top of that the vault must have grown by at least as much
+    /// since `last_known_vault_balance`, the snapshot every vault-touching
+    /// instruction refreshes on exit, which also stops a CPI caller from
+    /// crediting the one transfer twice. Lamports that reach the vault any
+    /// other way (donations, rent top-ups) credit nobody.
+    ///
+    /// Past the check it's `submit_message`: marketing, staking and penalty
+    /// cuts, `max_pot`, participant tracking and `client_nonce`, except the
+    /// marketing legs are paid back out of the vault. `expected_fee` is what's
+    /// charged; anything the transfer sent beyond the fee and penalty stays in
+    /// the vault as stray lamports. A repeated `client_nonce` fails here rather
+    /// than being a no-op, so the transfer before it rolls back too. Extra
+    /// marketing recipients follow the sysvar in the remaining accounts.
+    pub fn submit_message_precredited<'info>(
+        ctx: Context<'_, '_, '_, 'info, SubmitMessage<'info>>,
+        msg_hash: [u8; 32],
+        expected_fee: u64,
+        client_nonce: Option<u64>,
+    ) -> Result<()> {
+        let (instructions, extra_recipients) = ctx
+            .remaining_accounts
+            .split_first()
+            .ok_or(ErrorCode::PrecreditMissing)?;
+
+        let escrow = &ctx.accounts.escrow;
+        let clock = Clock::get()?;
+
+        let reason = ctx
+            .accounts
+            .submission_status(clock.unix_timestamp, false, client_nonce)?;
+        require!(reason == submit_reason::ELIGIBLE, submit_error(reason));
+        require!(
+            expected_fee >= escrow.fee_due(clock.unix_timestamp),
+            ErrorCode::InsufficientFee
+        );
+        escrow.emit_hold_reward(clock.unix_timestamp);
+
+        let penalty = escrow.self_submit_penalty(
+            &ctx.accounts.participant,
+            expected_fee,
+            clock.unix_timestamp,
+        );
+        let charge = expected_fee.checked_add(penalty).unwrap();
+        let transferred = precredited_transfer(
+            instructions,
+            &ctx.accounts.payer.key(),
+            &ctx.accounts.escrow_vault.key(),
+        )?;
+        let credited = ctx
+            .accounts
+            .escrow_vault
+            .lamports()
+            .saturating_sub(escrow.last_known_vault_balance);
+        require!(
+            transferred >= charge && credited >= charge,
+            ErrorCode::PrecreditMissing
+        );
+
+        let participant_bump = ctx.bumps.participant;
+        let vault_bump = ctx.bumps.escrow_vault;
+        ctx.accounts.accept_fee(
+            msg_hash,
+            expected_fee,
+            extra_recipients,
+            participant_bump,
+            clock.unix_timestamp,
+            Some(vault_bump),
+        )?;
+        if client_nonce.is_some() {
+            ctx.accounts.participant.last_client_nonce = client_nonce;
+        }
+
+        Ok(())
+    }
+
+    // The `deposit_mode` submit path. The whole fee goes to the vault as the
+    // payer's refundable deposit: no marketing skim, no self-submit penalty.
+    // Only the eventual winner's deposits join the pot; everyone else can
+    // take theirs back with `reclaim_deposit` once the game has ended.
+    pub fn submit_deposit(ctx: Context<SubmitMessage>, msg_hash: [u8; 32]) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        let clock = Clock::get()?;
+        let fee_paid = escrow.fee_due(clock.unix_timestamp);
+
+        ctx.accounts.require_accepting(clock.unix_timestamp, true)?;
+        let reason = escrow.fee_status(fee_paid, fee_paid, ctx.accounts.payer.lamports());
+        require!(reason == submit_reason::ELIGIBLE, submit_error(reason));
+        escrow.emit_hold_reward(clock.unix_timestamp);
+
+        invoke(
             &system_instruction::transfer(
+                &ctx.accounts.payer.key(),
                 &ctx.accounts.escrow_vault.key(),
-                &ctx.accounts.winner.key(),
-                balance,
+                fee_paid,
             ),
             &[
+                ctx.accounts.payer.to_account_info(),
                 ctx.accounts.escrow_vault.to_account_info(),
-                ctx.accounts.winner.to_account_info(),
                 ctx.accounts.system_program.to_account_info(),
             ],
-            &[signer_seeds],
         )?;
-    
-        // Emit event for indexing / frontend
-        emit!(PrizeClaimed {
-            winner: ctx.accounts.winner.key(),
-            amount: balance,
-        });
-    
-        Ok(())
-    }    
 
-    pub fn set_fee_params(ctx: Context<SetFeeParams>, base_fee: u64, fee_cap: u64) -> Result<()> {
         let escrow = &mut ctx.accounts.escrow;
-        
-        require!(base_fee > 0 && base_fee <= fee_cap, ErrorCode::BadParams);
-        
-        escrow.base_fee = base_fee;
-        escrow.fee_cap = fee_cap;
-        
-        if escrow.current_fee < base_fee {
-            escrow.current_fee = base_fee;
-        }
-        if escrow.current_fee > fee_cap {
-            escrow.current_fee = fee_cap;
+        escrow.last_known_vault_balance = ctx.accounts.escrow_vault.lamports();
+        escrow.deposits_outstanding = escrow.deposits_outstanding.checked_add(fee_paid).unwrap();
+
+        let payer = ctx.accounts.payer.key();
+        let participant = &mut ctx.accounts.participant;
+        join_game(escrow, participant, payer, ctx.bumps.participant, clock.unix_timestamp);
+        // a deposit goes back to its payer unless they win, so there's no
+        // prize leg for a snipe refund to come out of
+        record_submission(escrow, payer, msg_hash, fee_paid, 0, 0, clock.unix_timestamp)?;
+
+        track_participant(
+            escrow,
+            participant,
+            payer,
+            ctx.bumps.participant,
+            1,
+            fee_paid,
+            clock.unix_timestamp,
+        );
+        // anything left over from an earlier game rolled into that game's
+        // successor when it started
+        if participant.deposit_game != escrow.game_number {
+            participant.deposit_game = escrow.game_number;
+            participant.deposited = 0;
         }
+        participant.deposited = participant.deposited.checked_add(fee_paid).unwrap();
+        // the payer leads now, so these are the deposits a claim would release
+        escrow.leader_deposit = participant.deposited;
 
         Ok(())
     }
 
-    pub fn set_marketing_params(
+    // Hands a losing player's deposits for the ended game back out of the
+    // vault. Open until the next game starts, which `restart_game` holds
+    // off for `DEPOSIT_RECLAIM_WINDOW` while any are outstanding.
+    pub fn reclaim_deposit(ctx: Context<ReclaimDeposit>) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let participant = &mut ctx.accounts.participant;
+
+        require!(escrow.ended, ErrorCode::GameNotEnded);
+        require!(
+            participant.deposit_game == escrow.game_number && participant.deposited > 0,
+            ErrorCode::NoDepositToReclaim
+        );
+        require!(
+            ctx.accounts.claimant.key() != escrow.last_sender,
+            ErrorCode::Unauthorized
+        );
+
+        let vault = ctx.accounts.escrow_vault.lamports();
+        let spare = vault.saturating_sub(Rent::get()?.minimum_balance(0));
+        let amount = participant
+            .deposited
+            .min(escrow.deposits_outstanding)
+            .min(spare);
+
+        participant.deposited = 0;
+        escrow.deposits_outstanding -= amount;
+
+        if amount > 0 {
+            vault_transfer(
+                &ctx.accounts.escrow_vault.to_account_info(),
+                ctx.bumps.escrow_vault,
+                &ctx.accounts.claimant.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+                amount,
+            )?;
+        }
+        escrow.last_known_vault_balance = ctx.accounts.escrow_vault.lamports();
+
+        emit!(DepositReclaimed {
+            wallet: ctx.accounts.claimant.key(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    pub fn claim_prize<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimPrize<'info>>) -> Result<()> {
+        let winner = ctx.accounts.winner.key();
+        let amount = settle_claim(ctx, Vec::new())?;
+
+        emit!(PrizeClaimed { winner, amount });
+
+        Ok(())
+    }
+
+    // `claim_prize`, with the pot shared out between up to
+    // `MAX_PRIZE_SPLITS` wallets by bps (summing to 10_000) instead of all
+    // going to the winner. Each recipient is passed as a writable
+    // `remaining_accounts` entry in `recipients` order and gets its own
+    // `PrizeClaimed`. A sponsor top-up still goes to the signing winner.
+    pub fn claim_prize_split<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimPrize<'info>>,
+        recipients: Vec<PrizeSplit>,
+    ) -> Result<()> {
+        validate_prize_splits(&recipients)?;
+        require!(
+            ctx.remaining_accounts.len() == recipients.len(),
+            ErrorCode::BadParams
+        );
+
+        let mut prize_splits = Vec::with_capacity(recipients.len());
+        for (recipient, info) in recipients.iter().zip(ctx.remaining_accounts) {
+            require_keys_eq!(info.key(), recipient.wallet, ErrorCode::BadParams);
+            prize_splits.push((info.clone(), recipient.bps));
+        }
+
+        settle_claim(ctx, prize_splits)?;
+
+        Ok(())
+    }
+    
+
+    // same winner checks as `claim_prize`, but the pot stays in the vault to
+    // seed the next game
+    pub fn forfeit_prize(ctx: Context<ForfeitPrize>) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let clock = Clock::get()?;
+
+        // a game that never reached `min_unique_to_claim`, or whose winner is
+        // blocked as a self-deal, can't be claimed, so forfeiting is how it
+        // gets unstuck
+        let winner = ctx.accounts.winner.key();
+        let reason = escrow.claim_status(&ctx.accounts.config, winner, clock.unix_timestamp);
+        require!(
+            matches!(
+                reason,
+                claim_reason::ELIGIBLE
+                    | claim_reason::NOT_ENOUGH_PLAYERS
+                    | claim_reason::SELF_DEAL_BLOCKED
+            ),
+            claim_error(reason)
+        );
+
+        end_game(escrow, clock.unix_timestamp);
+
+        emit!(PrizeForfeited {
+            winner: ctx.accounts.winner.key(),
+            amount: ctx.accounts.escrow_vault.lamports(),
+        });
+
+        Ok(())
+    }
+
+    pub fn jigsaw_approve_payout(ctx: Context<JigsawApprovePayout>) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let clock = Clock::get()?;
+    
+        // --- validity checks ---
+        require!(!ctx.accounts.config.global_paused, ErrorCode::GloballyPaused);
+        require!(!escrow.ended, ErrorCode::AlreadyClaimed);
+        require!(
+            ctx.accounts.winner.key() == escrow.last_sender,
+            ErrorCode::NotTheWinner
+        );
+        require!(escrow.last_sender != Pubkey::default(), ErrorCode::NoWinner);
+        require!(
+            escrow.unique_participants >= escrow.min_unique_to_claim,
+            ErrorCode::NotEnoughPlayers
+        );
+        escrow.require_no_self_deal(ctx.accounts.winner.key())?;
+    
+        // Mark game as ended so it can't be claimed twice
+        end_game(escrow, clock.unix_timestamp);
+    
+        // Transfer the pot to the winner; the vault PDA signs via invoke_signed
+        let amount = pay_out(
+            escrow,
+            &ctx.accounts.config,
+            &PayoutAccounts {
+                escrow_vault: ctx.accounts.escrow_vault.to_account_info(),
+                vault_bump: ctx.bumps.escrow_vault,
+                sponsor_vault: ctx.accounts.sponsor_vault.to_account_info(),
+                sponsor_bump: ctx.bumps.sponsor_vault,
+                winner: ctx.accounts.winner.to_account_info(),
+                bonus_winner: ctx.accounts.bonus_winner.as_ref().map(|a| a.to_account_info()),
+                platform_wallet: ctx.accounts.platform_wallet.as_ref().map(|a| a.to_account_info()),
+                marketing_wallet: ctx.accounts.marketing_wallet.as_ref().map(|a| a.to_account_info()),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                prize_splits: Vec::new(),
+            },
+            0,
+            0,
+        )?;
+    
+        // Emit event for indexing / frontend
+        emit!(PrizeClaimed {
+            winner: ctx.accounts.winner.key(),
+            amount,
+        });
+        emit_admin_action(
+            admin_action::JIGSAW_APPROVE_PAYOUT,
+            ctx.accounts.jigsaw_approver.key(),
+        )?;
+    
+        Ok(())
+    }    
+
+    pub fn set_fee_params(ctx: Context<SetFeeParams>, base_fee: u64, fee_cap: u64) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        
+        require!(base_fee > 0 && base_fee <= fee_cap, ErrorCode::BadParams);
+        escrow.require_base_fee_change(base_fee)?;
+        escrow.note_param_change(Clock::get()?.unix_timestamp)?;
+        
+        escrow.base_fee = base_fee;
+        escrow.fee_cap = fee_cap;
+        
+        // the live fee moving under players' feet has to be visible
+        let old_fee = escrow.current_fee;
+        let reason = if old_fee < base_fee {
+            escrow.current_fee = base_fee;
+            fee_clamp_reason::BELOW_BASE_FEE
+        } else if old_fee > fee_cap {
+            escrow.current_fee = fee_cap;
+            fee_clamp_reason::ABOVE_FEE_CAP
+        } else {
+            fee_clamp_reason::UNCHANGED
+        };
+        if reason != fee_clamp_reason::UNCHANGED {
+            emit!(CurrentFeeClamped {
+                old_fee,
+                new_fee: escrow.current_fee,
+                reason,
+            });
+        }
+
+        emit_admin_action(admin_action::SET_FEE_PARAMS, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // Thin wrapper over `set_fee_params` taking whole SOL.
+    pub fn set_fee_params_sol(
+        ctx: Context<SetFeeParams>,
+        base_fee_sol: u64,
+        fee_cap_sol: u64,
+    ) -> Result<()> {
+        set_fee_params(
+            ctx,
+            sol_to_lamports(base_fee_sol)?,
+            sol_to_lamports(fee_cap_sol)?,
+        )
+    }
+
+    pub fn set_marketing_params(
         ctx: Context<SetMarketingParams>,
         wallet: Pubkey,
         bps: u16,
     ) -> Result<()> {
         let escrow = &mut ctx.accounts.escrow;
         
-        require!(bps <= 2500, ErrorCode::BpsTooHigh);
+        require!(bps <= MAX_MARKETING_BPS, ErrorCode::BpsTooHigh);
+        require!(escrow.staking_bps <= bps, ErrorCode::BadParams);
+        escrow.require_marketing_bps_change(bps)?;
+        require_marketing_wallet(&wallet, escrow.marketing_is_pda)?;
+        ctx.accounts
+            .config
+            .require_distinct_marketing_wallet(&escrow.authority, &wallet)?;
+        escrow.note_param_change(Clock::get()?.unix_timestamp)?;
         
         escrow.marketing_wallet = wallet;
         escrow.marketing_bps = bps;
+        escrow.set_single_marketing_recipient(wallet, bps);
 
         emit!(MarketingParamsUpdated {
             wallet,
             bps,
         });
+        emit_admin_action(admin_action::SET_MARKETING_PARAMS, ctx.accounts.authority.key())?;
 
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
+    // replaces the whole recipient list; `marketing_wallet` / `marketing_bps`
+    // become the first recipient and the combined share
+    pub fn set_marketing_splits(
+        ctx: Context<SetMarketingParams>,
+        recipients: Vec<MarketingSplit>,
+    ) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
 
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + Escrow::LEN,
-        seeds = [b"escrow"],
-        bump
-    )]
-    pub escrow: Account<'info, Escrow>,
+        let total_bps = validate_marketing_splits(&recipients)?;
+        require!(escrow.staking_bps <= total_bps, ErrorCode::BadParams);
+        require!(
+            recipients.len() == 1 || escrow.marketing_mode != marketing_mode::AT_CLAIM,
+            ErrorCode::BadParams
+        );
+        escrow.require_marketing_bps_change(total_bps)?;
+        for recipient in &recipients {
+            require_marketing_wallet(&recipient.wallet, escrow.marketing_is_pda)?;
+            ctx.accounts
+                .config
+                .require_distinct_marketing_wallet(&escrow.authority, &recipient.wallet)?;
+        }
+        escrow.note_param_change(Clock::get()?.unix_timestamp)?;
 
-    /// CHECK:
-    /// This PDA will be created in this instruction via `create_account`
-    /// and owned by this program. We don't read or trust any preexisting data.
-    #[account(
-        mut,
-        seeds = [b"escrow", b"vault"],
-        bump
-    )]
-    pub escrow_vault: SystemAccount<'info>,
+        escrow.marketing_wallet = recipients[0].wallet;
+        escrow.marketing_bps = total_bps;
+        escrow.marketing_splits = [MarketingSplit::default(); MAX_MARKETING_SPLITS];
+        escrow.marketing_splits[..recipients.len()].copy_from_slice(&recipients);
 
-    /// CHECK: arbitrary marketing wallet set by the authority; not controlled by program
-    pub marketing_wallet: UncheckedAccount<'info>,
+        for recipient in &recipients {
+            emit!(MarketingParamsUpdated {
+                wallet: recipient.wallet,
+                bps: recipient.bps,
+            });
+        }
+        emit_admin_action(admin_action::SET_MARKETING_SPLITS, ctx.accounts.authority.key())?;
 
-    pub system_program: Program<'info, System>,
-}
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct SubmitMessage<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
+    pub fn set_max_total_duration(
+        ctx: Context<SetMaxTotalDuration>,
+        max_total_duration: i64,
+    ) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
 
-    #[account(
-        mut,
-        seeds = [b"escrow"],
-        bump = escrow.bump
-    )]
-    pub escrow: Account<'info, Escrow>,
+        // 0 = unlimited
+        require!(max_total_duration >= 0, ErrorCode::BadParams);
 
-    /// CHECK:
-    /// This is the vault PDA (seeds ["escrow","vault"]) created in `initialize`.
-    /// It is owned by our program (not the system program) and just holds lamports.
-    /// We only ever move lamports via CPI using invoke_signed(), so we trust seeds+bump
-    /// instead of Anchor's owner check.
-    #[account(
-        mut,
-        seeds = [b"escrow", b"vault"],
-        bump
-    )]
-    pub escrow_vault: SystemAccount<'info>,
+        escrow.max_total_duration = max_total_duration;
 
-    /// CHECK:
-    /// This is the marketing wallet set by the authority; not controlled by program
-    /// Must be the wallet stored in escrow
-    #[account(
-        mut,
-        address = escrow.marketing_wallet @ ErrorCode::Unauthorized
-    )]
-    pub marketing_wallet: UncheckedAccount<'info>,
+        emit_admin_action(admin_action::SET_MAX_TOTAL_DURATION, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    pub fn initialize_config(ctx: Context<InitializeConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        config.admin = ctx.accounts.admin.key();
+        config.global_paused = false;
+        config.bump = ctx.bumps.config;
+        config.platform_wallet = Pubkey::default();
+        config.platform_claim_bps = 0;
+        config.require_distinct_marketing = false;
+
+        Ok(())
+    }
+
+    pub fn set_global_paused(ctx: Context<SetGlobalPaused>, paused: bool) -> Result<()> {
+        ctx.accounts.config.global_paused = paused;
+
+        emit!(GlobalPauseToggled {
+            admin: ctx.accounts.admin.key(),
+            paused,
+        });
+        emit_admin_action(admin_action::SET_GLOBAL_PAUSED, ctx.accounts.admin.key())?;
+
+        Ok(())
+    }
+
+    // platform-wide cut of every payout; `bps == 0` turns it off
+    pub fn set_platform_fee(ctx: Context<SetPlatformFee>, wallet: Pubkey, bps: u16) -> Result<()> {
+        require!(bps <= 2500, ErrorCode::BpsTooHigh);
+        require!(bps == 0 || wallet != Pubkey::default(), ErrorCode::BadParams);
+
+        let config = &mut ctx.accounts.config;
+        config.platform_wallet = wallet;
+        config.platform_claim_bps = bps;
+
+        emit_admin_action(admin_action::SET_PLATFORM_FEE, ctx.accounts.admin.key())?;
+
+        Ok(())
+    }
+
+    // platform-wide: new games must send marketing somewhere other than
+    // their own authority. Games already initialized aren't affected.
+    pub fn set_require_distinct_marketing(
+        ctx: Context<SetPlatformFee>,
+        required: bool,
+    ) -> Result<()> {
+        ctx.accounts.config.require_distinct_marketing = required;
+
+        emit_admin_action(
+            admin_action::SET_REQUIRE_DISTINCT_MARKETING,
+            ctx.accounts.admin.key(),
+        )?;
+
+        Ok(())
+    }
+
+    // manual knob for promos: moves the live fee only, bounds stay with
+    // `set_fee_params`. Later submissions keep compounding from `fee`.
+    pub fn set_current_fee(ctx: Context<SetCurrentFee>, fee: u64) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let clock = Clock::get()?;
+
+        require!(
+            fee >= escrow.base_fee && fee <= escrow.fee_cap,
+            ErrorCode::BadParams
+        );
+        escrow.require_current_fee_change(fee)?;
+        escrow.note_param_change(clock.unix_timestamp)?;
+
+        let old = escrow.current_fee;
+        escrow.current_fee = fee;
+
+        emit!(CurrentFeeOverridden { old, new: fee });
+        emit_admin_action(admin_action::SET_CURRENT_FEE, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // "back to base" without ending the game; the timer and pot are untouched
+    pub fn reset_fee(ctx: Context<SetCurrentFee>) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+
+        require!(!escrow.ended, ErrorCode::GameEnded);
+
+        let old = escrow.current_fee;
+        escrow.current_fee = escrow.base_fee.min(escrow.fee_cap);
+
+        emit!(CurrentFeeOverridden { old, new: escrow.current_fee });
+        emit_admin_action(admin_action::RESET_FEE, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    pub fn set_guaranteed_prize(ctx: Context<SetGuaranteedPrize>, amount: u64) -> Result<()> {
+        ctx.accounts.escrow.guaranteed_min_prize = amount;
+
+        emit_admin_action(admin_action::SET_GUARANTEED_PRIZE, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    pub fn set_restart_cooldown(ctx: Context<SetRestartCooldown>, cooldown: i64) -> Result<()> {
+        require!(cooldown >= 0, ErrorCode::BadParams);
+
+        ctx.accounts.escrow.restart_cooldown = cooldown;
+
+        emit_admin_action(admin_action::SET_RESTART_COOLDOWN, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // starts the next game in the same escrow once the previous one ended and
+    // `restart_cooldown` has elapsed
+    pub fn restart_game(ctx: Context<RestartGame>, base_fee: u64, fee_cap: u64) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let clock = Clock::get()?;
+
+        require!(escrow.ended, ErrorCode::GameNotEnded);
+        require!(
+            clock.unix_timestamp >= escrow.restart_allowed_at(),
+            ErrorCode::RestartTooSoon
+        );
+        require!(base_fee > 0 && base_fee <= fee_cap, ErrorCode::BadParams);
+
+        start_next_game(
+            escrow,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.escrow_vault.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            base_fee,
+            fee_cap,
+        )?;
+        emit_admin_action(admin_action::RESTART_GAME, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // `claim_prize` and `restart_game` in one transaction for perpetual games,
+    // so there's no window between the payout and the next game. Same
+    // eligibility checks as `claim_prize` and the restart cooldown must be 0.
+    // Both the authority and the winner (`last_sender`) sign: `rollover_bps`
+    // of the pot stays in the vault to seed the next game, and that's the
+    // winner's prize, so only the winner can agree to it.
+    pub fn claim_and_restart(
+        ctx: Context<ClaimAndRestart>,
+        base_fee: u64,
+        fee_cap: u64,
+        rollover_bps: u16,
+    ) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let clock = Clock::get()?;
+
+        require!(base_fee > 0 && base_fee <= fee_cap, ErrorCode::BadParams);
+        require!(rollover_bps <= 10_000, ErrorCode::BpsTooHigh);
+        require!(escrow.restart_cooldown == 0, ErrorCode::RestartTooSoon);
+
+        let winner = ctx.accounts.winner.key();
+        let reason = escrow.claim_status(&ctx.accounts.config, winner, clock.unix_timestamp);
+        require!(reason == claim_reason::ELIGIBLE, claim_error(reason));
+
+        end_game(escrow, clock.unix_timestamp);
+
+        let amount = pay_out(
+            escrow,
+            &ctx.accounts.config,
+            &PayoutAccounts {
+                escrow_vault: ctx.accounts.escrow_vault.to_account_info(),
+                vault_bump: ctx.bumps.escrow_vault,
+                sponsor_vault: ctx.accounts.sponsor_vault.to_account_info(),
+                sponsor_bump: ctx.bumps.sponsor_vault,
+                winner: ctx.accounts.winner.to_account_info(),
+                bonus_winner: ctx.accounts.bonus_winner.as_ref().map(|a| a.to_account_info()),
+                platform_wallet: ctx.accounts.platform_wallet.as_ref().map(|a| a.to_account_info()),
+                marketing_wallet: ctx.accounts.marketing_wallet.as_ref().map(|a| a.to_account_info()),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                prize_splits: Vec::new(),
+            },
+            rollover_bps,
+            0,
+        )?;
+
+        emit!(PrizeClaimed {
+            winner: ctx.accounts.winner.key(),
+            amount,
+        });
+
+        start_next_game(
+            escrow,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.escrow_vault.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            base_fee,
+            fee_cap,
+        )?;
+        emit_admin_action(admin_action::CLAIM_AND_RESTART, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    pub fn set_endgame_waive_marketing(
+        ctx: Context<SetEndgameWaiveMarketing>,
+        enabled: bool,
+    ) -> Result<()> {
+        ctx.accounts.escrow.endgame_waive_marketing = enabled;
+
+        emit_admin_action(
+            admin_action::SET_ENDGAME_WAIVE_MARKETING,
+            ctx.accounts.authority.key(),
+        )?;
+
+        Ok(())
+    }
+
+    // The short form of `winner_payout`: the vault balance, what stays in
+    // it for rent, and what the winner would receive right now. Same
+    // accounts and the same math, so the two can't disagree.
+    pub fn payout_preview(ctx: Context<ReadWinnerPayout>) -> Result<()> {
+        let payout = winner_payout_breakdown(ctx.accounts)?;
+
+        emit!(PayoutPreview {
+            gross: payout.gross,
+            retained_rent: payout.rent_retained,
+            net: payout.net,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_min_fee_increment(ctx: Context<SetMinFeeIncrement>, increment: u64) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+
+        // a step larger than the starting fee would at least double the price
+        // on every message
+        require!(increment <= escrow.base_fee, ErrorCode::BadParams);
+
+        escrow.min_fee_increment = increment;
+
+        emit_admin_action(admin_action::SET_MIN_FEE_INCREMENT, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    pub fn set_bonus_bps(ctx: Context<SetBonusBps>, bps: u16) -> Result<()> {
+        require!(bps <= 2500, ErrorCode::BpsTooHigh);
+
+        ctx.accounts.escrow.bonus_bps = bps;
+
+        emit_admin_action(admin_action::SET_BONUS_BPS, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    pub fn set_deadline_skew_tolerance(
+        ctx: Context<SetDeadlineSkewTolerance>,
+        tolerance: i64,
+    ) -> Result<()> {
+        require!(
+            (0..=MAX_DEADLINE_SKEW_TOLERANCE).contains(&tolerance),
+            ErrorCode::BadParams
+        );
+
+        ctx.accounts.escrow.deadline_skew_tolerance = tolerance;
+
+        emit_admin_action(admin_action::SET_DEADLINE_SKEW_TOLERANCE, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // `fee_usd_cents == 0` turns the USD path off again
+    pub fn set_usd_fee(ctx: Context<SetUsdFee>, fee_usd_cents: u64, oracle: Pubkey) -> Result<()> {
+        require!(
+            fee_usd_cents == 0 || oracle != Pubkey::default(),
+            ErrorCode::BadParams
+        );
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.fee_usd_cents = fee_usd_cents;
+        escrow.oracle = oracle;
+
+        emit_admin_action(admin_action::SET_USD_FEE, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // Pre-provisions zeroed space on the escrow account ahead of an upgrade
+    // that appends fields, so the new code never meets an account too small
+    // to hold them. The realloc itself happens in the accounts constraint,
+    // paid by the authority.
+    pub fn grow_escrow(ctx: Context<GrowEscrow>, additional: u32) -> Result<()> {
+        let new_len = ctx.accounts.escrow.to_account_info().data_len();
+
+        require!(additional > 0, ErrorCode::BadParams);
+        require!(
+            new_len <= 8 + Escrow::LEN + MAX_ESCROW_GROWTH,
+            ErrorCode::BadParams
+        );
+
+        emit!(EscrowResized { new_len: new_len as u32 });
+        emit_admin_action(admin_action::GROW_ESCROW, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    pub fn set_strict_deadline(ctx: Context<SetStrictDeadline>, strict: bool) -> Result<()> {
+        ctx.accounts.escrow.strict_deadline = strict;
+
+        emit_admin_action(admin_action::SET_STRICT_DEADLINE, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // window = 0 goes back to "every submission extends"
+    pub fn set_anti_snipe(
+        ctx: Context<SetAntiSnipe>,
+        window: i64,
+        extension: i64,
+    ) -> Result<()> {
+        require!(window >= 0 && extension >= 0, ErrorCode::BadParams);
+        require!(window == 0 || extension > 0, ErrorCode::BadParams);
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.anti_snipe_window = window;
+        escrow.anti_snipe_extension = extension;
+
+        emit_admin_action(admin_action::SET_ANTI_SNIPE, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // lets anyone check a revealed message against the leading commitment
+    pub fn verify_message(ctx: Context<VerifyMessage>, preimage: Vec<u8>) -> Result<()> {
+        require!(preimage.len() <= MAX_PREIMAGE_LEN, ErrorCode::BadParams);
+
+        let digest = hash(&preimage).to_bytes();
+
+        emit!(MessageVerified {
+            matches: digest == ctx.accounts.escrow.winning_msg_hash,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_strict_accounting(ctx: Context<SetStrictAccounting>, strict: bool) -> Result<()> {
+        ctx.accounts.escrow.strict_accounting = strict;
+
+        emit_admin_action(admin_action::SET_STRICT_ACCOUNTING, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // Under `strict_accounting`, hands lamports that reached the vault outside
+    // the fee flow back to the authority. The vault keeps `accounted_balance`
+    // (and at least its rent-exempt minimum).
+    pub fn sweep_unaccounted(ctx: Context<SweepUnaccounted>) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+
+        require!(escrow.strict_accounting, ErrorCode::BadParams);
+
+        let keep = escrow
+            .accounted_balance
+            .checked_add(escrow.deposits_outstanding)
+            .unwrap()
+            .max(Rent::get()?.minimum_balance(0));
+        let amount = ctx.accounts.escrow_vault.lamports().saturating_sub(keep);
+
+        if amount > 0 {
+            vault_transfer(
+                &ctx.accounts.escrow_vault.to_account_info(),
+                ctx.bumps.escrow_vault,
+                &ctx.accounts.authority.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+                amount,
+            )?;
+        }
+        escrow.last_known_vault_balance = ctx.accounts.escrow_vault.lamports();
+
+        emit!(UnaccountedSwept { amount });
+        emit_admin_action(admin_action::SWEEP_UNACCOUNTED, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    pub fn set_params_locked_after(ctx: Context<SetParamsLockedAfter>, messages: u64) -> Result<()> {
+        ctx.accounts.escrow.params_locked_after = messages;
+
+        emit_admin_action(admin_action::SET_PARAMS_LOCKED_AFTER, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // `Pubkey::default()` turns the NFT gate off
+    pub fn set_required_collection(
+        ctx: Context<SetRequiredCollection>,
+        collection: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.escrow.required_collection = collection;
+
+        emit_admin_action(admin_action::SET_REQUIRED_COLLECTION, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // carves `bps` of every fee out of the marketing share for the staking
+    // PDA. Enabling it tops the PDA up to rent-exempt so the first small
+    // staking transfer doesn't fail.
+    pub fn set_staking_bps(ctx: Context<SetStakingBps>, bps: u16) -> Result<()> {
+        require!(bps <= ctx.accounts.escrow.marketing_bps, ErrorCode::BadParams);
+        require!(
+            bps == 0 || ctx.accounts.escrow.marketing_mode != marketing_mode::AT_CLAIM,
+            ErrorCode::BadParams
+        );
+
+        let rent_lamports = Rent::get()?.minimum_balance(0);
+        let staking_lamports = ctx.accounts.staking_vault.lamports();
+        if bps > 0 && staking_lamports < rent_lamports {
+            invoke(
+                &system_instruction::transfer(
+                    &ctx.accounts.authority.key(),
+                    &ctx.accounts.staking_vault.key(),
+                    rent_lamports - staking_lamports,
+                ),
+                &[
+                    ctx.accounts.authority.to_account_info(),
+                    ctx.accounts.staking_vault.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+
+        ctx.accounts.escrow.staking_bps = bps;
+
+        emit_admin_action(admin_action::SET_STAKING_BPS, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // canonical addresses and bumps as the program derives them, so clients
+    // don't have to trust their own `find_program_address`
+    pub fn resolve_pdas(_ctx: Context<ResolvePdas>) -> Result<()> {
+        let (escrow, escrow_bump) = Pubkey::find_program_address(&[b"escrow"], &crate::ID);
+        let (escrow_vault, vault_bump) =
+            Pubkey::find_program_address(&[b"escrow", b"vault"], &crate::ID);
+
+        emit!(ResolvedPdas {
+            escrow,
+            escrow_vault,
+            escrow_bump,
+            vault_bump,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_marketing_is_pda(ctx: Context<SetMarketingIsPda>, is_pda: bool) -> Result<()> {
+        ctx.accounts.escrow.marketing_is_pda = is_pda;
+
+        emit_admin_action(admin_action::SET_MARKETING_IS_PDA, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // cooldown = 0 or bps = 0 turns the penalty off
+    pub fn set_self_submit_penalty(
+        ctx: Context<SetSelfSubmitPenalty>,
+        cooldown: i64,
+        bps: u16,
+    ) -> Result<()> {
+        require!(cooldown >= 0, ErrorCode::BadParams);
+        require!(bps <= 10_000, ErrorCode::BpsTooHigh);
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.self_submit_cooldown = cooldown;
+        escrow.self_submit_penalty_bps = bps;
+
+        emit_admin_action(admin_action::SET_SELF_SUBMIT_PENALTY, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // circuit breaker independent of `fee_cap`; `u64::MAX` = off
+    pub fn set_max_single_fee(ctx: Context<SetMaxSingleFee>, max_fee: u64) -> Result<()> {
+        require!(max_fee > 0, ErrorCode::BadParams);
+
+        ctx.accounts.escrow.max_single_fee = max_fee;
+
+        emit_admin_action(admin_action::SET_MAX_SINGLE_FEE, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // 0 = unlimited; once used up, submissions still count but the deadline
+    // stops moving
+    pub fn set_max_extensions(ctx: Context<SetMaxExtensions>, max_extensions: u64) -> Result<()> {
+        ctx.accounts.escrow.max_extensions = max_extensions;
+
+        emit_admin_action(admin_action::SET_MAX_EXTENSIONS, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    pub fn set_min_unique_to_claim(ctx: Context<SetMinUniqueToClaim>, min_unique: u64) -> Result<()> {
+        require!(min_unique >= 1, ErrorCode::BadParams);
+        // raising it once the timer runs could lock the leader out of a pot
+        // they've already won
+        let escrow = &mut ctx.accounts.escrow;
+        require!(
+            !escrow.timer_active || escrow.ended,
+            ErrorCode::ParamsLockedDuringEndgame
+        );
+
+        escrow.min_unique_to_claim = min_unique;
+
+        emit_admin_action(admin_action::SET_MIN_UNIQUE_TO_CLAIM, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // Holds `claim_prize` back for `delay` seconds after the deadline so a
+    // disputed result can still be handled through `jigsaw_approve_payout`
+    // before the winner pays themselves out.
+    pub fn set_claim_delay(ctx: Context<SetClaimDelay>, delay: i64) -> Result<()> {
+        require!((0..=MAX_CLAIM_DELAY).contains(&delay), ErrorCode::BadParams);
+        // fixed for the endgame, so a pending claim can't be pushed back
+        let escrow = &mut ctx.accounts.escrow;
+        require!(
+            !escrow.timer_active || escrow.ended,
+            ErrorCode::ParamsLockedDuringEndgame
+        );
+
+        escrow.claim_delay_after_deadline = delay;
+
+        emit_admin_action(admin_action::SET_CLAIM_DELAY, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // `interval == 0` turns the hold reward off
+    pub fn set_hold_reward(ctx: Context<SetHoldReward>, interval: i64, bps: u16) -> Result<()> {
+        require!(interval >= 0, ErrorCode::BadParams);
+        require!(bps <= 10_000, ErrorCode::BpsTooHigh);
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.hold_reward_interval = interval;
+        escrow.hold_reward_bps = bps;
+
+        emit_admin_action(admin_action::SET_HOLD_REWARD, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // Pins the game to end at `end_time`: the timer runs from now on whatever
+    // the message count, and submissions are still accepted until then but
+    // no longer extend it. Cleared by the next restart.
+    pub fn schedule_end(ctx: Context<ScheduleEnd>, end_time: i64) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let clock = Clock::get()?;
+
+        require!(!escrow.ended, ErrorCode::GameEnded);
+        require!(end_time > clock.unix_timestamp, ErrorCode::BadParams);
+
+        escrow.deadline = end_time;
+        escrow.timer_active = true;
+        escrow.scheduled_end = true;
+        escrow.deadline_reached = false;
+
+        emit!(EndScheduled { end_time });
+        emit_admin_action(admin_action::SCHEDULE_END, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    pub fn set_max_submissions_per_slot(
+        ctx: Context<SetMaxSubmissionsPerSlot>,
+        max: u64,
+    ) -> Result<()> {
+        require!(max > 0, ErrorCode::BadParams);
+
+        ctx.accounts.escrow.max_submissions_per_slot = max;
+
+        emit_admin_action(
+            admin_action::SET_MAX_SUBMISSIONS_PER_SLOT,
+            ctx.accounts.authority.key(),
+        )?;
+
+        Ok(())
+    }
+
+    // caps marketing (staking included) per game; `u64::MAX` is uncapped
+    pub fn set_marketing_cap(ctx: Context<SetMarketingCap>, cap: u64) -> Result<()> {
+        ctx.accounts.escrow.marketing_cap = cap;
+
+        emit_admin_action(admin_action::SET_MARKETING_CAP, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // minimum marketing per message when the bps cut rounds below it; 0 = off
+    pub fn set_marketing_min(ctx: Context<SetMarketingMin>, min: u64) -> Result<()> {
+        ctx.accounts.escrow.marketing_min = min;
+
+        emit_admin_action(admin_action::SET_MARKETING_MIN, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // Freezing lets the current deadline run out while submissions keep
+    // landing (and growing the pot); it doesn't stop the timer from starting.
+    pub fn set_extensions_frozen(ctx: Context<SetExtensionsFrozen>, frozen: bool) -> Result<()> {
+        ctx.accounts.escrow.extensions_frozen = frozen;
+
+        emit!(ExtensionsFrozenToggled { frozen });
+        emit_admin_action(admin_action::SET_EXTENSIONS_FROZEN, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // Only before the first game's first message, so every game's players
+    // know up front when the rake is taken. Claim-time marketing pays the
+    // primary `marketing_wallet` only, so it excludes extra splits and
+    // staking.
+    pub fn set_marketing_mode(ctx: Context<SetMarketingMode>, mode: u8) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+
+        require!(mode <= marketing_mode::AT_CLAIM, ErrorCode::BadParams);
+        require!(
+            escrow.game_number == 0 && escrow.messages_count == 0,
+            ErrorCode::ParamsLocked
+        );
+        if mode == marketing_mode::AT_CLAIM {
+            require!(
+                escrow.staking_bps == 0
+                    && escrow.marketing_splits[1..].iter().all(|split| split.bps == 0),
+                ErrorCode::BadParams
+            );
+        }
+
+        escrow.marketing_mode = mode;
+
+        emit!(MarketingModeSet { mode });
+        emit_admin_action(admin_action::SET_MARKETING_MODE, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // 0 turns sniped refunds off; a refund already owed stays claimable
+    pub fn set_sniped_refund_bps(ctx: Context<SetSnipedRefundBps>, bps: u16) -> Result<()> {
+        require!(bps <= 10_000, ErrorCode::BpsTooHigh);
+
+        ctx.accounts.escrow.sniped_refund_bps = bps;
+
+        emit_admin_action(admin_action::SET_SNIPED_REFUND_BPS, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // last-moment floor on what a submission is charged, after any discount;
+    // 0 = off
+    pub fn set_min_fee_paid(ctx: Context<SetMinFeePaid>, min: u64) -> Result<()> {
+        ctx.accounts.escrow.min_fee_paid = min;
+
+        emit_admin_action(admin_action::SET_MIN_FEE_PAID, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // 0 lets every submission extend, as before
+    pub fn set_min_unique_for_extension(
+        ctx: Context<SetMinUniqueForExtension>,
+        min: u64,
+    ) -> Result<()> {
+        ctx.accounts.escrow.min_unique_for_extension = min;
+
+        emit_admin_action(
+            admin_action::SET_MIN_UNIQUE_FOR_EXTENSION,
+            ctx.accounts.authority.key(),
+        )?;
+
+        Ok(())
+    }
+
+    // 0 turns the winner's gas top-up off. Like the winner rebate, the
+    // top-up is part of the pot: it's set aside before claim-time marketing,
+    // the platform fee and the bonus, so it only makes a difference when at
+    // least one of those cuts would otherwise leave the winner short.
+    pub fn set_gas_reserve(ctx: Context<SetGasReserve>, lamports: u64) -> Result<()> {
+        require!(lamports <= MAX_GAS_RESERVE, ErrorCode::BadParams);
+
+        ctx.accounts.escrow.gas_reserve = lamports;
+
+        emit_admin_action(admin_action::SET_GAS_RESERVE, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // minimum gap between fee/marketing parameter changes; 0 = no limit
+    pub fn set_param_change_cooldown(
+        ctx: Context<SetParamChangeCooldown>,
+        cooldown: i64,
+    ) -> Result<()> {
+        require!(cooldown >= 0, ErrorCode::BadParams);
+
+        ctx.accounts.escrow.param_change_cooldown = cooldown;
+
+        emit_admin_action(admin_action::SET_PARAM_CHANGE_COOLDOWN, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // 0 turns post-deadline decay off
+    pub fn set_post_deadline_decay(
+        ctx: Context<SetPostDeadlineDecay>,
+        bps_per_hour: u16,
+    ) -> Result<()> {
+        require!(bps_per_hour <= MAX_POST_DEADLINE_DECAY_BPS, ErrorCode::BpsTooHigh);
+
+        ctx.accounts.escrow.post_deadline_decay_bps_per_hour = bps_per_hour;
+
+        emit_admin_action(admin_action::SET_POST_DEADLINE_DECAY, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // opt-in anti-rug policy: the operator's own wallets can't take the pot
+    pub fn set_prevent_self_deal(ctx: Context<SetPreventSelfDeal>, prevent: bool) -> Result<()> {
+        ctx.accounts.escrow.prevent_self_deal = prevent;
+
+        emit_admin_action(admin_action::SET_PREVENT_SELF_DEAL, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // `u64::MAX` leaves the pot uncapped
+    pub fn set_max_pot(ctx: Context<SetMaxPot>, max_pot: u64) -> Result<()> {
+        require!(max_pot > 0, ErrorCode::BadParams);
+
+        ctx.accounts.escrow.max_pot = max_pot;
+
+        emit_admin_action(admin_action::SET_MAX_POT, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // 0 lets submissions run right up to the deadline
+    pub fn set_settlement_buffer(ctx: Context<SetSettlementBuffer>, buffer: i64) -> Result<()> {
+        require!(buffer >= 0, ErrorCode::BadParams);
+
+        ctx.accounts.escrow.settlement_buffer = buffer;
+
+        emit_admin_action(admin_action::SET_SETTLEMENT_BUFFER, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // Switches between fee and deposit games. Only between games, with no
+    // deposits left to hand back.
+    pub fn set_deposit_mode(ctx: Context<SetDepositMode>, enabled: bool) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+
+        require!(
+            escrow.messages_count == 0 && escrow.deposits_outstanding == 0,
+            ErrorCode::ParamsLocked
+        );
+
+        escrow.deposit_mode = enabled;
+
+        emit_admin_action(admin_action::SET_DEPOSIT_MODE, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // `u64::MAX` lets any number of new wallets join
+    pub fn set_max_participants(ctx: Context<SetMaxParticipants>, max: u64) -> Result<()> {
+        require!(max > 0, ErrorCode::BadParams);
+
+        ctx.accounts.escrow.max_participants = max;
+
+        emit_admin_action(admin_action::SET_MAX_PARTICIPANTS, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    // The rebate comes out of the pot itself, not from outside it: it's set
+    // aside before claim-time marketing, the platform fee and the bonus, so
+    // all it does is shield that much of the pot from those cuts. With none
+    // of them on, the winner gets the whole pot either way and the rebate
+    // changes nothing.
+    pub fn set_winner_rebate_bps(ctx: Context<SetWinnerRebateBps>, bps: u16) -> Result<()> {
+        require!(bps <= 10_000, ErrorCode::BpsTooHigh);
+
+        ctx.accounts.escrow.winner_rebate_bps = bps;
+
+        emit_admin_action(admin_action::SET_WINNER_REBATE_BPS, ctx.accounts.authority.key())?;
+
+        Ok(())
+    }
+
+    pub fn recent_senders(ctx: Context<ReadRecentSenders>) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+
+        emit!(RecentSenders {
+            senders: escrow.recent_senders,
+            head: escrow.recent_senders_head,
+        });
+
+        Ok(())
+    }
+
+    pub fn recent_fees(ctx: Context<ReadRecentFees>) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+
+        emit!(RecentFees {
+            fees: escrow.recent_fees,
+            head: escrow.recent_fees_head,
+        });
+
+        Ok(())
+    }
+
+    pub fn extensions_remaining(ctx: Context<ReadExtensionsRemaining>) -> Result<()> {
+        emit!(ExtensionsRemaining {
+            remaining: ctx.accounts.escrow.extensions_remaining(),
+        });
+
+        Ok(())
+    }
+
+    // Would taking the lead now pay off if it held? `pot` is what the signer
+    // would take home after this submission lands: the payable vault plus
+    // the prize leg of their fee, net of the platform fee and bonus, with
+    // their rebate (on this game's fees including this one) added back.
+    pub fn break_even(ctx: Context<BreakEvenCheck>) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        let clock = Clock::get()?;
+
+        let fee = escrow.fee_due(clock.unix_timestamp);
+        let (_, prize_fee) = escrow.split_fee(fee);
+        let balance = escrow
+            .payable_balance(ctx.accounts.escrow_vault.lamports())
+            .checked_add(prize_fee)
+            .unwrap();
+
+        let contributed = ctx
+            .accounts
+            .participant
+            .as_ref()
+            .filter(|p| p.game_number == escrow.game_number)
+            .map_or(0, |p| p.contributed);
+        let rebate = escrow.winner_rebate_cut(contributed.checked_add(fee).unwrap());
+        let pot = escrow.winner_take(&ctx.accounts.config, balance, rebate);
+
+        emit!(BreakEven {
+            current_fee: fee,
+            pot,
+            is_profitable: pot > fee,
+        });
+
+        Ok(())
+    }
+
+    // What `claim_prize` would hand the winner right now, cut by cut; see
+    // `winner_payout_breakdown`.
+    pub fn winner_payout(ctx: Context<ReadWinnerPayout>) -> Result<()> {
+        emit!(winner_payout_breakdown(ctx.accounts)?);
+
+        Ok(())
+    }
+
+    // Everything that decides what a submission costs, in one event. The
+    // fee only ever compounds, so `fee_mode` is always 0 and `fee_floor` is
+    // `base_fee` for now; both are reserved for when that changes.
+    pub fn fee_policy(ctx: Context<ReadFeePolicy>) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+
+        emit!(FeePolicy {
+            base_fee: escrow.base_fee,
+            fee_cap: escrow.fee_cap,
+            current_fee: escrow.current_fee,
+            fee_growth_bps: FEE_GROWTH_BPS,
+            fee_mode: 0,
+            fee_step: escrow.next_fee().saturating_sub(escrow.current_fee),
+            min_fee_increment: escrow.min_fee_increment,
+            fee_floor: escrow.base_fee,
+            marketing_bps: escrow.marketing_bps,
+        });
+
+        Ok(())
+    }
+
+    // One-call books for the current game (the one just settled, until the
+    // next restart): what came in, what went out as rake and prize, and
+    // `net`, the difference. `net` goes negative when the prize outgrew this
+    // game's fees, e.g. on a rolled-over or sponsored pot.
+    pub fn economic_summary(ctx: Context<Checkpoint>) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+
+        let net = escrow.total_fees_collected as i128
+            - escrow.total_marketing_paid as i128
+            - escrow.total_prize_paid as i128;
+
+        emit!(EconomicSummary {
+            total_fees_collected: escrow.total_fees_collected,
+            total_marketing_paid: escrow.total_marketing_paid,
+            total_prize_paid: escrow.total_prize_paid,
+            current_vault_balance: ctx.accounts.escrow_vault.lamports(),
+            net,
+        });
+
+        Ok(())
+    }
+
+    // The `security_txt!` values plus the program id, for clients that can't
+    // read the binary's `.security.txt` section.
+    pub fn security_info(_ctx: Context<ReadSecurityInfo>) -> Result<()> {
+        emit!(SecurityInfo {
+            program_id: crate::ID,
+            name: SECURITY_NAME.to_string(),
+            project_url: SECURITY_PROJECT_URL.to_string(),
+            contacts: SECURITY_CONTACTS.to_string(),
+            policy: SECURITY_POLICY.to_string(),
+            source_code: SECURITY_SOURCE_CODE.to_string(),
+        });
+
+        Ok(())
+    }
+
+    // Resync anchor for indexers coming back from downtime: the live game
+    // state in one event, stamped with the slot it was read at. Events missed
+    // before that slot can be dropped in favour of this snapshot.
+    pub fn checkpoint(ctx: Context<Checkpoint>) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+
+        emit!(CheckpointTaken {
+            messages_count: escrow.messages_count,
+            current_fee: escrow.current_fee,
+            deadline: escrow.deadline,
+            timer_active: escrow.timer_active,
+            ended: escrow.ended,
+            last_sender: escrow.last_sender,
+            pot: escrow.payable_balance(ctx.accounts.escrow_vault.lamports()),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    // `checkpoint` with nothing left out: the whole escrow account, counters
+    // included, for an indexer rebuilding from scratch rather than catching up.
+    pub fn full_resync(ctx: Context<Checkpoint>) -> Result<()> {
+        emit!(FullState {
+            state: Box::new((*ctx.accounts.escrow).clone()),
+            vault_balance: ctx.accounts.escrow_vault.lamports(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    // Pays the most recently sniped leader their `sniped_refund` out of the
+    // pot. Only while the game is live; the payout takes whatever is left.
+    pub fn claim_sniped_refund(ctx: Context<ClaimSnipedRefund>) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+
+        require!(!escrow.ended, ErrorCode::GameEnded);
+        require!(
+            escrow.sniped_refund > 0 && escrow.sniped_wallet == ctx.accounts.claimant.key(),
+            ErrorCode::Unauthorized
+        );
+
+        let vault = ctx.accounts.escrow_vault.lamports();
+        let spare = vault.saturating_sub(Rent::get()?.minimum_balance(0));
+        let amount = escrow.sniped_refund.min(escrow.payable_balance(vault)).min(spare);
+
+        escrow.sniped_refund = 0;
+        escrow.sniped_wallet = Pubkey::default();
+        escrow.accounted_balance = escrow.accounted_balance.saturating_sub(amount);
+
+        if amount > 0 {
+            vault_transfer(
+                &ctx.accounts.escrow_vault.to_account_info(),
+                ctx.bumps.escrow_vault,
+                &ctx.accounts.claimant.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+                amount,
+            )?;
+        }
+        escrow.last_known_vault_balance = ctx.accounts.escrow_vault.lamports();
+
+        emit!(SnipedRefund {
+            wallet: ctx.accounts.claimant.key(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless. Records that the claim window has opened so a later
+    // claim can't be refused by a clock that reads earlier (e.g. across a
+    // fork). Only a submission that moves the deadline clears the latch.
+    pub fn latch_deadline(ctx: Context<LatchDeadline>) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let clock = Clock::get()?;
+
+        require!(
+            escrow.timer_active
+                && !escrow.ended
+                && clock.unix_timestamp >= escrow.claimable_from(),
+            ErrorCode::GameNotEnded
+        );
+
+        if !escrow.deadline_reached {
+            escrow.deadline_reached = true;
+            emit!(DeadlineLatched {
+                deadline: escrow.deadline,
+            });
+        }
+
+        Ok(())
+    }
+
+    // Permissionless. Once the claim window has been open for whole hours,
+    // burns `post_deadline_decay_bps_per_hour` of the pot per hour to the
+    // incinerator, measured against the pot when decay first applied and
+    // capped at `MAX_POST_DEADLINE_DECAY_BPS` of it. Repeat calls only burn
+    // the hours not yet accounted for.
+    pub fn apply_decay(ctx: Context<ApplyDecay>) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let clock = Clock::get()?;
+
+        require!(
+            escrow.timer_active
+                && !escrow.ended
+                && (clock.unix_timestamp >= escrow.claimable_from() || escrow.deadline_reached),
+            ErrorCode::GameNotEnded
+        );
+        require!(escrow.post_deadline_decay_bps_per_hour > 0, ErrorCode::BadParams);
+
+        let vault = ctx.accounts.escrow_vault.lamports();
+        let pot = escrow.payable_balance(vault);
+        if escrow.decay_base == 0 {
+            escrow.decay_base = pot;
+        }
+
+        let (due, hours) = escrow.decay_due(pot, clock.unix_timestamp);
+        let spare = vault.saturating_sub(Rent::get()?.minimum_balance(0));
+        let burned = due.min(spare);
+
+        if burned > 0 {
+            escrow.decay_burned = escrow.decay_burned.checked_add(burned).unwrap();
+            escrow.accounted_balance = escrow.accounted_balance.saturating_sub(burned);
+            vault_transfer(
+                &ctx.accounts.escrow_vault.to_account_info(),
+                ctx.bumps.escrow_vault,
+                &ctx.accounts.incinerator.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+                burned,
+            )?;
+            escrow.last_known_vault_balance = ctx.accounts.escrow_vault.lamports();
+
+            emit!(PrizeDecayed { burned, hours });
+        }
+
+        Ok(())
+    }
+
+    // `jigsaw_approve_payout` is gated on `escrow.authority` as well, so for
+    // now the two flags always agree; they're separate so a dedicated
+    // approver key doesn't change the event layout.
+    pub fn am_i_authority(ctx: Context<AmIAuthority>) -> Result<()> {
+        let is_authority = ctx.accounts.escrow.authority == ctx.accounts.caller.key();
+
+        emit!(AuthorityCheck {
+            is_authority,
+            is_jigsaw_approver: is_authority,
+        });
+
+        Ok(())
+    }
+
+    // The configured splitter without the fixed-size padding. `MarketingSplit`
+    // rather than a tuple so the IDL can describe it.
+    pub fn marketing_recipients(ctx: Context<ReadMarketingRecipients>) -> Result<()> {
+        let recipients = ctx
+            .accounts
+            .escrow
+            .marketing_splits
+            .iter()
+            .filter(|split| split.bps > 0)
+            .copied()
+            .collect();
+
+        emit!(MarketingRecipients { recipients });
+
+        Ok(())
+    }
+
+    // Receipt that `wallet` has played. The PDA only exists once it has, so a
+    // missing account is the "never played" answer. `count` and
+    // `contributed` cover the wallet's latest game, `first_ts` its first ever
+    // submission.
+    pub fn participation_proof(ctx: Context<ReadParticipation>, wallet: Pubkey) -> Result<()> {
+        let participant = &ctx.accounts.participant;
+        require_keys_eq!(participant.wallet, wallet, ErrorCode::BadParams);
+
+        emit!(ParticipationProof {
+            wallet,
+            count: participant.messages_count,
+            contributed: participant.contributed,
+            first_ts: participant.first_ts,
+        });
+
+        Ok(())
+    }
+
+    // Once a game has ended, turns up to `MAX_CONSOLATION_ACCOUNTS`
+    // `Participant` PDAs (as `remaining_accounts`) into a list of the wallets
+    // that played it and lost, for an off-chain airdrop. Every account must be
+    // a genuine, distinct participant PDA; the winner and wallets whose
+    // latest game is a different one are left out. Moves no funds.
+    pub fn emit_consolation_list<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ReadConsolationList<'info>>,
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        require!(escrow.ended, ErrorCode::GameNotEnded);
+        require!(
+            ctx.remaining_accounts.len() <= MAX_CONSOLATION_ACCOUNTS,
+            ErrorCode::BadParams
+        );
+
+        let mut wallets: Vec<Pubkey> = Vec::with_capacity(ctx.remaining_accounts.len());
+        let mut seen: Vec<Pubkey> = Vec::with_capacity(ctx.remaining_accounts.len());
+        for info in ctx.remaining_accounts {
+            let participant = Account::<Participant>::try_from(info)?;
+            let pda = Pubkey::create_program_address(
+                &[
+                    b"participant",
+                    participant.wallet.as_ref(),
+                    &[participant.bump],
+                ],
+                &crate::ID,
+            )
+            .map_err(|_| error!(ErrorCode::BadParams))?;
+            require_keys_eq!(pda, info.key(), ErrorCode::BadParams);
+            require!(!seen.contains(&participant.wallet), ErrorCode::BadParams);
+            seen.push(participant.wallet);
+
+            if participant.game_number == escrow.game_number
+                && participant.wallet != escrow.last_sender
+            {
+                wallets.push(participant.wallet);
+            }
+        }
+
+        emit!(ConsolationList { wallets });
+
+        Ok(())
+    }
+
+    // When does the game end if someone submits every `submit_interval`
+    // seconds from now on? Replays the timer rules on a scratch copy of the
+    // escrow for up to `MAX_PROJECTION_STEPS` submissions; `end_time` is
+    // `i64::MAX` if it's still running after that. `hit_cap` reports whether
+    // `max_extensions` or `max_total_duration` had to stop the extensions.
+    pub fn project_end(ctx: Context<ReadProjectedEnd>, submit_interval: i64) -> Result<()> {
+        require!(submit_interval > 0, ErrorCode::BadParams);
+        require!(!ctx.accounts.escrow.ended, ErrorCode::GameEnded);
+
+        let mut sim = Box::new(Escrow::clone(&ctx.accounts.escrow));
+        let mut now = Clock::get()?.unix_timestamp;
+        let mut hit_cap = false;
+        let mut end_time = i64::MAX;
+
+        for _ in 0..MAX_PROJECTION_STEPS {
+            let at = now.checked_add(submit_interval).unwrap();
+            if sim.timer_active
+                && (!sim.before_submission_deadline(at) || sim.in_settlement_window(at))
+            {
+                end_time = sim.deadline;
+                break;
+            }
+            now = at;
+
+            if sim.messages_count == 0 {
+                sim.first_message_ts = now;
+            }
+            sim.messages_count += 1;
+            if !sim.timer_active {
+                if sim.messages_count >= START_AFTER {
+                    sim.timer_active = true;
+                    sim.deadline = sim.next_deadline(now);
+                }
+                continue;
+            }
+
+            if let Some(deadline) = sim.extended_deadline(now) {
+                sim.deadline = deadline;
+                sim.extension_count += 1;
+            } else if sim.max_extensions > 0 && sim.extensions_remaining() == 0 {
+                hit_cap = true;
+            }
+            if sim.max_total_duration > 0
+                && sim.deadline == sim.first_message_ts + sim.max_total_duration
+            {
+                hit_cap = true;
+            }
+        }
+
+        emit!(ProjectedEnd { end_time, hit_cap });
+
+        Ok(())
+    }
+
+    pub fn leader_info(ctx: Context<ReadLeaderInfo>) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        let clock = Clock::get()?;
+
+        // once the game is over the hold stops counting at `ended_ts`
+        let until = if escrow.ended { escrow.ended_ts } else { clock.unix_timestamp };
+        let held_seconds = if escrow.last_sender == Pubkey::default() {
+            0
+        } else {
+            until.saturating_sub(escrow.leader_since).max(0)
+        };
+
+        emit!(LeaderInfo {
+            leader: escrow.last_sender,
+            leader_since: escrow.leader_since,
+            held_seconds,
+        });
+
+        Ok(())
+    }
+
+    pub fn can_i_claim(ctx: Context<CanIClaim>) -> Result<()> {
+        let clock = Clock::get()?;
+        let reason = ctx.accounts.escrow.claim_status(
+            &ctx.accounts.config,
+            ctx.accounts.caller.key(),
+            clock.unix_timestamp,
+        );
+
+        emit!(ClaimEligibility {
+            eligible: reason == claim_reason::ELIGIBLE,
+            reason_code: reason,
+            amount: ctx.accounts.escrow_vault.lamports(),
+        });
+
+        Ok(())
+    }
+
+    // The signer's personal price right now. The only per-wallet adjustment
+    // today is the self-submit penalty, plus the hold reward everyone sees;
+    // `effective_fee` is what `submit_message` would take from them.
+    pub fn my_fee(ctx: Context<ReadMyFee>) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        let now = Clock::get()?.unix_timestamp;
+
+        let fee = escrow.fee_due(now);
+        let penalty = ctx
+            .accounts
+            .participant
+            .as_ref()
+            .map_or(0, |p| escrow.self_submit_penalty(p, fee, now));
+
+        emit!(MyFee {
+            base_current_fee: escrow.current_fee,
+            effective_fee: fee.checked_add(penalty).unwrap(),
+            applied_discount_bps: if fee < escrow.current_fee {
+                escrow.hold_reward_bps
+            } else {
+                0
+            },
+            applied_penalty_bps: if penalty > 0 {
+                escrow.self_submit_penalty_bps
+            } else {
+                0
+            },
+        });
+
+        Ok(())
+    }
+
+    // Dry run of `submit_message` for the signer: same guards, no transfers.
+    // `required_fee` includes any self-submit penalty the payer would pay.
+    // `client_nonce` is the one the real call would carry, if any.
+    pub fn can_submit(ctx: Context<CanSubmit>, client_nonce: Option<u64>) -> Result<()> {
+        let clock = Clock::get()?;
+        let escrow = &ctx.accounts.escrow;
+        let participant = ctx.accounts.participant.as_deref();
+        let fee = escrow.fee_due(clock.unix_timestamp);
+        let penalty =
+            participant.map_or(0, |p| escrow.self_submit_penalty(p, fee, clock.unix_timestamp));
+        let required_fee = fee.checked_add(penalty).unwrap();
+
+        let attempt = SubmitAttempt {
+            participant,
+            client_nonce,
+            vault_lamports: ctx.accounts.escrow_vault.lamports(),
+            deposit: false,
+            now: clock.unix_timestamp,
+            slot: clock.slot,
+        };
+        let mut reason = submission_status(&ctx.accounts.config, escrow, &attempt);
+        if reason == submit_reason::ELIGIBLE {
+            reason = escrow.fee_status(fee, required_fee, ctx.accounts.payer.lamports());
+        }
+
+        emit!(SubmitEligibility {
+            eligible: reason == submit_reason::ELIGIBLE,
+            reason_code: reason,
+            required_fee,
+        });
+
+        Ok(())
+    }
+}
+
+/// `action_code` values carried by [`AdminAction`]. Codes are append-only so
+/// indexers can keep decoding historical events.
+pub mod admin_action {
+    pub const JIGSAW_APPROVE_PAYOUT: u8 = 1;
+    pub const SET_FEE_PARAMS: u8 = 2;
+    pub const SET_MARKETING_PARAMS: u8 = 3;
+    pub const SET_MAX_TOTAL_DURATION: u8 = 4;
+    pub const SET_GLOBAL_PAUSED: u8 = 5;
+    pub const SET_CURRENT_FEE: u8 = 6;
+    pub const SET_GUARANTEED_PRIZE: u8 = 7;
+    pub const SET_RESTART_COOLDOWN: u8 = 8;
+    pub const RESTART_GAME: u8 = 9;
+    pub const SET_ENDGAME_WAIVE_MARKETING: u8 = 10;
+    pub const SET_MIN_FEE_INCREMENT: u8 = 11;
+    pub const SET_MARKETING_SPLITS: u8 = 12;
+    pub const SET_BONUS_BPS: u8 = 13;
+    pub const SET_DEADLINE_SKEW_TOLERANCE: u8 = 14;
+    pub const SET_USD_FEE: u8 = 15;
+    pub const RESET_FEE: u8 = 16;
+    pub const GROW_ESCROW: u8 = 17;
+    pub const SET_PLATFORM_FEE: u8 = 18;
+    pub const SET_STRICT_DEADLINE: u8 = 19;
+    pub const SET_ANTI_SNIPE: u8 = 20;
+    pub const SET_STRICT_ACCOUNTING: u8 = 21;
+    pub const SWEEP_UNACCOUNTED: u8 = 22;
+    pub const SET_PARAMS_LOCKED_AFTER: u8 = 23;
+    pub const SET_REQUIRED_COLLECTION: u8 = 24;
+    pub const SET_STAKING_BPS: u8 = 25;
+    pub const SET_MARKETING_IS_PDA: u8 = 26;
+    pub const SET_SELF_SUBMIT_PENALTY: u8 = 27;
+    pub const CLAIM_AND_RESTART: u8 = 28;
+    pub const SET_MAX_SINGLE_FEE: u8 = 29;
+    pub const SET_MAX_EXTENSIONS: u8 = 30;
+    pub const SET_MIN_UNIQUE_TO_CLAIM: u8 = 31;
+    pub const SET_WINNER_REBATE_BPS: u8 = 32;
+    pub const SET_CLAIM_DELAY: u8 = 33;
+    pub const SET_HOLD_REWARD: u8 = 34;
+    pub const SCHEDULE_END: u8 = 35;
+    pub const SET_MAX_SUBMISSIONS_PER_SLOT: u8 = 36;
+    pub const SET_MARKETING_CAP: u8 = 37;
+    pub const SET_MARKETING_MIN: u8 = 38;
+    pub const SET_EXTENSIONS_FROZEN: u8 = 39;
+    pub const SET_MARKETING_MODE: u8 = 40;
+    pub const SET_SNIPED_REFUND_BPS: u8 = 41;
+    pub const SET_MIN_FEE_PAID: u8 = 42;
+    pub const SET_MIN_UNIQUE_FOR_EXTENSION: u8 = 43;
+    pub const SET_GAS_RESERVE: u8 = 44;
+    pub const SET_PARAM_CHANGE_COOLDOWN: u8 = 45;
+    pub const SET_POST_DEADLINE_DECAY: u8 = 46;
+    pub const SET_PREVENT_SELF_DEAL: u8 = 47;
+    pub const SET_REQUIRE_DISTINCT_MARKETING: u8 = 48;
+    pub const SET_MAX_POT: u8 = 49;
+    pub const SET_SETTLEMENT_BUFFER: u8 = 50;
+    pub const SET_DEPOSIT_MODE: u8 = 51;
+    pub const SET_MAX_PARTICIPANTS: u8 = 52;
+}
+
+/// `reason_code` values carried by [`ClaimEligibility`], one per `claim_prize`
+/// guard in the order they are checked.
+pub mod claim_reason {
+    pub const ELIGIBLE: u8 = 0;
+    pub const GLOBALLY_PAUSED: u8 = 1;
+    pub const TIMER_NOT_ACTIVE: u8 = 2;
+    pub const DEADLINE_NOT_REACHED: u8 = 3;
+    pub const NO_WINNER: u8 = 4;
+    pub const ALREADY_CLAIMED: u8 = 5;
+    pub const NOT_THE_WINNER: u8 = 6;
+    pub const NOT_ENOUGH_PLAYERS: u8 = 7;
+    /// `prevent_self_deal` is on and the caller is the authority or takes a
+    /// share of the marketing rake
+    pub const SELF_DEAL_BLOCKED: u8 = 8;
+}
+
+fn claim_error(reason: u8) -> ErrorCode {
+    match reason {
+        claim_reason::GLOBALLY_PAUSED => ErrorCode::GloballyPaused,
+        claim_reason::NO_WINNER => ErrorCode::NoWinner,
+        claim_reason::ALREADY_CLAIMED => ErrorCode::AlreadyClaimed,
+        claim_reason::NOT_THE_WINNER => ErrorCode::NotTheWinner,
+        claim_reason::NOT_ENOUGH_PLAYERS => ErrorCode::NotEnoughPlayers,
+        claim_reason::SELF_DEAL_BLOCKED => ErrorCode::SelfDealBlocked,
+        _ => ErrorCode::GameNotEnded,
+    }
+}
+
+/// When the marketing skim is taken, see [`Escrow::marketing_mode`].
+pub mod marketing_mode {
+    /// `marketing_bps` of every fee, as it's paid
+    pub const PER_SUBMISSION: u8 = 0;
+    /// full fees go to the pot; `marketing_bps` of the pot is taken at payout
+    pub const AT_CLAIM: u8 = 1;
+}
+
+/// `reason` values carried by [`CurrentFeeClamped`].
+pub mod fee_clamp_reason {
+    pub const UNCHANGED: u8 = 0;
+    /// raised to a new, higher `base_fee`
+    pub const BELOW_BASE_FEE: u8 = 1;
+    /// lowered to a new, lower `fee_cap`
+    pub const ABOVE_FEE_CAP: u8 = 2;
+}
+
+/// `reason_code` values carried by [`SubmitEligibility`], one per
+/// `submit_message` guard in the order they are checked.
+pub mod submit_reason {
+    pub const ELIGIBLE: u8 = 0;
+    /// `client_nonce` repeats the payer's last one; `submit_message` would
+    /// be a no-op
+    pub const DUPLICATE_SUBMIT: u8 = 1;
+    pub const GLOBALLY_PAUSED: u8 = 2;
+    /// the game is (or isn't) in `deposit_mode`, so this submit path is closed
+    pub const DEPOSIT_MODE: u8 = 3;
+    pub const GAME_ENDED: u8 = 4;
+    pub const TIMER_EXPIRED: u8 = 5;
+    pub const SETTLEMENT_WINDOW: u8 = 6;
+    pub const VAULT_NOT_INITIALIZED: u8 = 7;
+    /// `max_participants` reached and the payer has no `Participant` PDA yet
+    pub const PARTICIPANT_LIMIT: u8 = 8;
+    /// `max_submissions_per_slot` already used up in this slot
+    pub const SLOT_LIMIT: u8 = 9;
+    pub const FEE_TOO_LARGE: u8 = 10;
+    pub const FEE_TOO_LOW: u8 = 11;
+    pub const INSUFFICIENT_FUNDS: u8 = 12;
+}
+
+fn submit_error(reason: u8) -> ErrorCode {
+    match reason {
+        submit_reason::DUPLICATE_SUBMIT => ErrorCode::DuplicateSubmit,
+        submit_reason::GLOBALLY_PAUSED => ErrorCode::GloballyPaused,
+        submit_reason::DEPOSIT_MODE => ErrorCode::DepositModeMismatch,
+        submit_reason::GAME_ENDED => ErrorCode::GameEnded,
+        submit_reason::TIMER_EXPIRED => ErrorCode::TimerExpired,
+        submit_reason::SETTLEMENT_WINDOW => ErrorCode::SettlementWindow,
+        submit_reason::VAULT_NOT_INITIALIZED => ErrorCode::VaultNotInitialized,
+        submit_reason::PARTICIPANT_LIMIT => ErrorCode::ParticipantLimitReached,
+        submit_reason::SLOT_LIMIT => ErrorCode::SlotSubmissionLimit,
+        submit_reason::FEE_TOO_LARGE => ErrorCode::FeeTooLarge,
+        submit_reason::FEE_TOO_LOW => ErrorCode::FeeTooLow,
+        _ => ErrorCode::InsufficientFee,
+    }
+}
+
+// largest fee the `*_sol` wrappers accept; anything bigger is far more
+// likely a lamport amount passed by mistake than a real price
+pub const MAX_FEE_SOL: u64 = 1_000;
+
+fn sol_to_lamports(sol: u64) -> Result<u64> {
+    require!(sol <= MAX_FEE_SOL, ErrorCode::BadParams);
+    Ok(sol * LAMPORTS_PER_SOL)
+}
+
+// upper bound for `gas_reserve` (0.01 SOL), a few claim transactions' worth
+pub const MAX_GAS_RESERVE: u64 = 10_000_000;
+
+// how long losers get to `reclaim_deposit` before the next game may start
+// and roll whatever is left into its pot
+pub const DEPOSIT_RECLAIM_WINDOW: i64 = 7 * 24 * 60 * 60;
+
+// most of a pot `apply_decay` can ever burn
+pub const MAX_POST_DEADLINE_DECAY_BPS: u16 = 5000;
+
+// most `Participant` accounts one `emit_consolation_list` call will check
+pub const MAX_CONSOLATION_ACCOUNTS: usize = 24;
+
+// upper bound on simulated submissions in `project_end`
+pub const MAX_PROJECTION_STEPS: usize = 512;
+
+// per-submission fee growth (0.78%)
+pub const FEE_GROWTH_BPS: u16 = 78;
+
+// timer rules
+pub const START_AFTER: u64 = 10;
+pub const EXTEND_SECONDS: i64 = 3600;
+
+// single audit stream for every authority-gated instruction, emitted on top
+// of whatever instruction-specific event it already has
+fn emit_admin_action(action_code: u8, actor: Pubkey) -> Result<()> {
+    emit!(AdminAction {
+        action_code,
+        actor,
+        slot: Clock::get()?.slot,
+    });
+    Ok(())
+}
+
+// flips `ended` (before any lamports move, so a payout can't run twice) and
+// records when it happened for the restart cooldown
+fn end_game(escrow: &mut Escrow, now: i64) {
+    escrow.ended = true;
+    escrow.ended_ts = now;
+
+    emit!(GameEnded {
+        winner: escrow.last_sender,
+        ended_ts: now,
+        claimable_from: escrow.claimable_from(),
+        restart_allowed_at: escrow.restart_allowed_at(),
+        max_fee_paid: escrow.max_fee_paid,
+    });
+}
+
+// Resets `escrow` for the next game, topping the vault back up to rent-exempt
+// out of `authority` first, and emits `GameRestarted`.
+fn start_next_game<'info>(
+    escrow: &mut Escrow,
+    authority: &AccountInfo<'info>,
+    escrow_vault: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    base_fee: u64,
+    fee_cap: u64,
+) -> Result<()> {
+    // losers get their reclaim window; after it, unclaimed deposits are the
+    // next game's pot
+    require!(
+        escrow.deposits_outstanding == 0
+            || Clock::get()?.unix_timestamp
+                >= escrow.ended_ts.saturating_add(DEPOSIT_RECLAIM_WINDOW),
+        ErrorCode::DepositsOutstanding
+    );
+    escrow.accounted_balance = escrow
+        .accounted_balance
+        .checked_add(escrow.deposits_outstanding)
+        .unwrap();
+    escrow.deposits_outstanding = 0;
+    escrow.leader_deposit = 0;
+
+    // a claim drains the vault; put it back to rent-exempt so the first
+    // small prize transfer of the new game doesn't fail
+    let rent_lamports = Rent::get()?.minimum_balance(0);
+    let vault_lamports = escrow_vault.lamports();
+    if vault_lamports < rent_lamports {
+        invoke(
+            &system_instruction::transfer(
+                &authority.key(),
+                &escrow_vault.key(),
+                rent_lamports - vault_lamports,
+            ),
+            &[authority.clone(), escrow_vault.clone(), system_program.clone()],
+        )?;
+    }
+
+    escrow.base_fee = base_fee;
+    escrow.fee_cap = fee_cap;
+    escrow.current_fee = base_fee;
+    escrow.messages_count = 0;
+    escrow.last_sender = Pubkey::default();
+    escrow.leader_since = 0;
+    escrow.sniped_wallet = Pubkey::default();
+    escrow.sniped_refund = 0;
+    escrow.last_prize_fee = 0;
+    escrow.winning_msg_hash = [0; 32];
+    escrow.timer_active = false;
+    escrow.deadline = 0;
+    escrow.deadline_reached = false;
+    escrow.scheduled_end = false;
+    escrow.ended = false;
+    escrow.ended_ts = 0;
+    escrow.first_message_ts = 0;
+    escrow.max_fee_paid = 0;
+    escrow.recent_senders = [Pubkey::default(); RECENT_SENDERS];
+    escrow.recent_senders_head = 0;
+    escrow.recent_fees = [0; RECENT_FEES];
+    escrow.recent_fees_head = 0;
+    escrow.game_number = escrow.game_number.checked_add(1).unwrap();
+    escrow.unique_participants = 0;
+    escrow.extension_count = 0;
+    escrow.total_marketing_paid = 0;
+    escrow.total_fees_collected = 0;
+    escrow.total_prize_paid = 0;
+    escrow.decay_base = 0;
+    escrow.decay_burned = 0;
+    escrow.last_known_vault_balance = escrow_vault.lamports();
+
+    emit!(GameRestarted {
+        base_fee,
+        fee_cap,
+        vault_balance: escrow.last_known_vault_balance,
+    });
+
+    Ok(())
+}
+
+// How much of `shortfall` the sponsor PDA can cover out of `available`.
+fn sponsor_draw(shortfall: u64, available: u64, rent_lamports: u64) -> u64 {
+    let paid = shortfall.min(available);
+    // a partial draw has to leave the sponsor vault rent-exempt
+    if paid < available && available - paid < rent_lamports {
+        return available.saturating_sub(rent_lamports);
+    }
+    paid
+}
+
+/// Accounts every payout path moves lamports through.
+pub struct PayoutAccounts<'info> {
+    pub escrow_vault: AccountInfo<'info>,
+    pub vault_bump: u8,
+    pub sponsor_vault: AccountInfo<'info>,
+    pub sponsor_bump: u8,
+    pub winner: AccountInfo<'info>,
+    pub bonus_winner: Option<AccountInfo<'info>>,
+    pub platform_wallet: Option<AccountInfo<'info>>,
+    pub marketing_wallet: Option<AccountInfo<'info>>,
+    pub system_program: AccountInfo<'info>,
+    /// wallets sharing the winner's payout, with their bps; empty = all to
+    /// `winner`
+    pub prize_splits: Vec<(AccountInfo<'info>, u16)>,
+}
+
+// SystemProgram transfer out of the vault PDA, signed with
+// [b"escrow", b"vault", bump]
+fn vault_transfer<'info>(
+    escrow_vault: &AccountInfo<'info>,
+    vault_bump: u8,
+    to: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let escrow_seed: &[u8] = b"escrow";
+    let vault_seed: &[u8] = b"vault";
+    let bump_seed: &[u8] = &[vault_bump];
+    let signer_seeds: &[&[u8]] = &[escrow_seed, vault_seed, bump_seed];
+
+    invoke_signed(
+        &system_instruction::transfer(&escrow_vault.key(), &to.key(), amount),
+        &[escrow_vault.clone(), to.clone(), system_program.clone()],
+        &[signer_seeds],
+    )?;
+    Ok(())
+}
+
+fn transfer_from_vault<'info>(
+    accounts: &PayoutAccounts<'info>,
+    to: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    vault_transfer(
+        &accounts.escrow_vault,
+        accounts.vault_bump,
+        to,
+        &accounts.system_program,
+        amount,
+    )
+}
+
+// Everything `claim_prize` does short of announcing it: the winner checks,
+// ending the game and `pay_out`. With `prize_splits` the winner's share is
+// sent to those wallets instead. Returns what `pay_out` does.
+fn settle_claim<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ClaimPrize<'info>>,
+    prize_splits: Vec<(AccountInfo<'info>, u16)>,
+) -> Result<u64> {
+    let escrow = &mut ctx.accounts.escrow;
+    let clock = Clock::get()?;
+
+    // log the inputs to whichever guard failed; only on the failure path
+    // so a successful claim doesn't pay for the formatting
+    let winner = ctx.accounts.winner.key();
+    let reason = escrow.claim_status(&ctx.accounts.config, winner, clock.unix_timestamp);
+    if reason == claim_reason::GLOBALLY_PAUSED {
+        msg!("claim rejected: protocol paused");
+    } else if reason != claim_reason::ELIGIBLE {
+        msg!(
+            "claim rejected: reason={} now={} deadline={} claimable_from={} timer_active={} ended={}",
+            reason,
+            clock.unix_timestamp,
+            escrow.deadline,
+            escrow.claimable_from(),
+            escrow.timer_active,
+            escrow.ended
+        );
+        msg!(
+            "caller={} last_sender={} unique={}/{}",
+            ctx.accounts.winner.key(),
+            escrow.last_sender,
+            escrow.unique_participants,
+            escrow.min_unique_to_claim
+        );
+    }
+    require!(reason == claim_reason::ELIGIBLE, claim_error(reason));
+
+    end_game(escrow, clock.unix_timestamp);
+
+    let rebate = escrow.winner_rebate(ctx.accounts.winner_participant.as_deref());
+
+    // Pay the pot out of the vault PDA (signed with its seeds)
+    pay_out(
+        escrow,
+        &ctx.accounts.config,
+        &PayoutAccounts {
+            escrow_vault: ctx.accounts.escrow_vault.to_account_info(),
+            vault_bump: ctx.bumps.escrow_vault,
+            sponsor_vault: ctx.accounts.sponsor_vault.to_account_info(),
+            sponsor_bump: ctx.bumps.sponsor_vault,
+            winner: ctx.accounts.winner.to_account_info(),
+            bonus_winner: ctx.accounts.bonus_winner.as_ref().map(|a| a.to_account_info()),
+            platform_wallet: ctx.accounts.platform_wallet.as_ref().map(|a| a.to_account_info()),
+            marketing_wallet: ctx.accounts.marketing_wallet.as_ref().map(|a| a.to_account_info()),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            prize_splits,
+        },
+        0,
+        rebate,
+    )
+}
+
+// Pays out the vault once a game has been settled (`ended` must already be
+// set): claim-time marketing (if that mode is on) and the platform fee first,
+// then the optional recent-sender bonus out of what's left, everything else
+// to the winner, then any sponsor top-up. `rollover_bps` of the pot is held
+// back in the vault for the next game, and `rebate` (capped at what's left)
+// is carved out for the winner before any of those cuts are taken, as is any
+// `gas_reserve` top-up. Returns what the winner received.
+fn pay_out(
+    escrow: &mut Escrow,
+    config: &Config,
+    accounts: &PayoutAccounts<'_>,
+    rollover_bps: u16,
+    rebate: u64,
+) -> Result<u64> {
+    let rent_lamports = Rent::get()?.minimum_balance(0);
+
+    // anything above `max_pot` (donations, sponsor top-ups, precredited
+    // fees) stays in the vault for the next game
+    let (balance, _) = escrow.payout_pot(accounts.escrow_vault.lamports(), 0, rent_lamports);
+    let split = escrow.payout_split(
+        config,
+        balance,
+        rollover_bps,
+        rebate,
+        accounts.winner.lamports(),
+        rent_lamports,
+    );
+
+    // the winner's own deposits are the one part of `deposit_mode` stakes
+    // that joins the pot
+    escrow.deposits_outstanding = escrow.deposits_outstanding.saturating_sub(escrow.leader_deposit);
+    escrow.leader_deposit = 0;
+    escrow.accounted_balance = split.rollover;
+
+    if split.rebate > 0 {
+        emit!(WinnerRebate { amount: split.rebate });
+    }
+    if split.gas > 0 {
+        emit!(GasReserveApplied { amount: split.gas });
+    }
+
+    // a winner who is also the marketing wallet gets both shares in one
+    // transfer instead of two to the same account
+    let combined = escrow.marketing_mode == marketing_mode::AT_CLAIM
+        && accounts.winner.key() == escrow.marketing_wallet;
+    pay_claim_marketing(escrow, split.marketing, accounts, combined)?;
+    pay_platform_fee(config, split.platform_fee, accounts)?;
+    pay_bonus(escrow, split.bonus, accounts)?;
+    let payout = if combined { split.prize + split.marketing } else { split.prize };
+
+    if accounts.prize_splits.is_empty() {
+        transfer_from_vault(accounts, &accounts.winner, payout)?;
+    } else {
+        pay_prize_splits(accounts, payout)?;
+    }
+    escrow.last_known_vault_balance = accounts.escrow_vault.lamports();
+    if combined {
+        emit!(CombinedPayout {
+            wallet: accounts.winner.key(),
+            amount: payout,
+        });
+    }
+
+    // the guarantee covers the prize, not the marketing share riding along
+    let sponsored = pay_guarantee_shortfall(escrow, split.prize, accounts)?;
+    let paid = payout.checked_add(sponsored).unwrap();
+    escrow.total_prize_paid = escrow.total_prize_paid.checked_add(paid).unwrap();
+
+    Ok(paid)
+}
+
+// What `claim_prize` would hand the winner right now, cut by cut, from
+// the same `payout_pot` / `payout_split` math `pay_out` runs. Decay that
+// has accrued but not been burned yet is counted as gone, since anyone
+// can `apply_decay` ahead of the claim. `net` includes the rebate, gas
+// top-up, any combined marketing share and the sponsor guarantee.
+fn winner_payout_breakdown(accounts: &ReadWinnerPayout) -> Result<WinnerPayout> {
+    let escrow = &accounts.escrow;
+    let rent_lamports = Rent::get()?.minimum_balance(0);
+
+    let gross = accounts.escrow_vault.lamports();
+    let decay = if escrow.ended {
+        0
+    } else {
+        escrow
+            .decay_due(escrow.payable_balance(gross), Clock::get()?.unix_timestamp)
+            .0
+            .min(gross.saturating_sub(rent_lamports))
+    };
+    let (balance, rent_retained) = escrow.payout_pot(gross, decay, rent_lamports);
+    let split = escrow.payout_split(
+        &accounts.config,
+        balance,
+        0,
+        escrow.winner_rebate(accounts.winner_participant.as_deref()),
+        accounts.winner.lamports(),
+        rent_lamports,
+    );
+
+    let combined = escrow.marketing_mode == marketing_mode::AT_CLAIM
+        && escrow.last_sender == escrow.marketing_wallet;
+    let sponsored = sponsor_draw(
+        escrow.guaranteed_min_prize.saturating_sub(split.prize),
+        accounts.sponsor_vault.lamports(),
+        rent_lamports,
+    );
+    let net = split
+        .prize
+        .checked_add(if combined { split.marketing } else { 0 })
+        .unwrap()
+        .checked_add(sponsored)
+        .unwrap();
+
+    Ok(WinnerPayout {
+        gross,
+        platform_fee: split.platform_fee,
+        marketing: split.marketing,
+        bonus: split.bonus,
+        decay,
+        rent_retained,
+        net,
+    })
+}
+
+// Shares `payout` out over `accounts.prize_splits` by bps, one
+// `PrizeClaimed` per recipient. The last recipient also absorbs the rounding
+// dust.
+fn pay_prize_splits(accounts: &PayoutAccounts<'_>, payout: u64) -> Result<()> {
+    let last = accounts.prize_splits.len() - 1;
+    let mut left = payout;
+    for (i, (recipient, bps)) in accounts.prize_splits.iter().enumerate() {
+        let share = if i == last {
+            left
+        } else {
+            (payout as u128)
+                .checked_mul(*bps as u128)
+                .unwrap()
+                .checked_div(10_000)
+                .unwrap() as u64
+        };
+        left -= share;
+        if share > 0 {
+            transfer_from_vault(accounts, recipient, share)?;
+        }
+
+        emit!(PrizeClaimed {
+            winner: recipient.key(),
+            amount: share,
+        });
+    }
+    Ok(())
+}
+
+// In `marketing_mode::AT_CLAIM`, the whole game's marketing skim taken at once:
+// `amount` (`marketing_bps` of the pot, still bounded by `marketing_cap`) to
+// `marketing_wallet`, which the caller has to pass. With `combined` the
+// winner is the marketing wallet and the caller folds the amount into the
+// prize transfer instead.
+fn pay_claim_marketing(
+    escrow: &mut Escrow,
+    amount: u64,
+    accounts: &PayoutAccounts<'_>,
+    combined: bool,
+) -> Result<()> {
+    if escrow.marketing_mode != marketing_mode::AT_CLAIM || escrow.marketing_bps == 0 {
+        return Ok(());
+    }
+
+    if !combined {
+        let recipient = accounts
+            .marketing_wallet
+            .as_ref()
+            .ok_or(ErrorCode::Unauthorized)?;
+        require_keys_eq!(recipient.key(), escrow.marketing_wallet, ErrorCode::Unauthorized);
+        if amount > 0 {
+            transfer_from_vault(accounts, recipient, amount)?;
+        }
+    }
+    escrow.add_marketing_paid(amount);
+
+    emit!(ClaimMarketingCollected {
+        wallet: escrow.marketing_wallet,
+        amount,
+    });
+
+    Ok(())
+}
+
+// Sends `amount` (`platform_claim_bps` of the pot) to `config.platform_wallet`,
+// which the caller has to pass as `platform_wallet` whenever the fee is on.
+fn pay_platform_fee(config: &Config, amount: u64, accounts: &PayoutAccounts<'_>) -> Result<()> {
+    if config.platform_claim_bps == 0 {
+        return Ok(());
+    }
+    let recipient = accounts
+        .platform_wallet
+        .as_ref()
+        .ok_or(ErrorCode::PlatformWalletMismatch)?;
+    require_keys_eq!(
+        recipient.key(),
+        config.platform_wallet,
+        ErrorCode::PlatformWalletMismatch
+    );
+
+    if amount > 0 {
+        transfer_from_vault(accounts, recipient, amount)?;
+    }
+
+    emit!(PlatformFeeCollected {
+        wallet: config.platform_wallet,
+        amount,
+    });
+
+    Ok(())
+}
+
+// Sends `bonus_bps` of the pot to the recent sender picked by
+// `Escrow::bonus_index`. The caller has to pass that wallet as `bonus_winner`.
+fn pay_bonus(escrow: &Escrow, amount: u64, accounts: &PayoutAccounts<'_>) -> Result<()> {
+    let Some(index) = escrow.bonus_index() else {
+        return Ok(());
+    };
+    let wallet = escrow.recent_senders[index];
+    let recipient = accounts
+        .bonus_winner
+        .as_ref()
+        .ok_or(ErrorCode::BonusWinnerMismatch)?;
+    require_keys_eq!(recipient.key(), wallet, ErrorCode::BonusWinnerMismatch);
+
+    if amount > 0 {
+        transfer_from_vault(accounts, recipient, amount)?;
+    }
+
+    emit!(BonusAwarded {
+        wallet,
+        amount,
+        index: index as u8,
+    });
+
+    Ok(())
+}
+
+// Tops the winner up from the sponsor PDA when the prize is below
+// `guaranteed_min_prize`. Pays whatever the sponsor can cover and emits
+// `GuaranteeShortfall` for the rest. Returns the lamports the sponsor paid.
+fn pay_guarantee_shortfall(
+    escrow: &Escrow,
+    prize: u64,
+    accounts: &PayoutAccounts<'_>,
+) -> Result<u64> {
+    if prize >= escrow.guaranteed_min_prize {
+        return Ok(0);
+    }
+    let shortfall = escrow.guaranteed_min_prize - prize;
+    let paid = sponsor_draw(
+        shortfall,
+        accounts.sponsor_vault.lamports(),
+        Rent::get()?.minimum_balance(0),
+    );
+
+    if paid > 0 {
+        let escrow_seed: &[u8] = b"escrow";
+        let sponsor_seed: &[u8] = b"sponsor";
+        let bump_seed: &[u8] = &[accounts.sponsor_bump];
+        let signer_seeds: &[&[u8]] = &[escrow_seed, sponsor_seed, bump_seed];
+
+        invoke_signed(
+            &system_instruction::transfer(
+                &accounts.sponsor_vault.key(),
+                &accounts.winner.key(),
+                paid,
+            ),
+            &[
+                accounts.sponsor_vault.clone(),
+                accounts.winner.clone(),
+                accounts.system_program.clone(),
+            ],
+            &[signer_seeds],
+        )?;
+    }
+
+    if paid < shortfall {
+        emit!(GuaranteeShortfall {
+            guaranteed: escrow.guaranteed_min_prize,
+            paid: prize.checked_add(paid).unwrap(),
+            uncovered: shortfall - paid,
+        });
+    }
+
+    Ok(paid)
+}
+
+// upper bound for `deadline_skew_tolerance`; validator clocks drift by
+// seconds, not minutes
+pub const MAX_DEADLINE_SKEW_TOLERANCE: i64 = 60;
+
+// upper bound for `claim_delay_after_deadline`; long enough to review a
+// disputed result, short enough that a winner isn't held hostage
+pub const MAX_CLAIM_DELAY: i64 = 7 * 24 * 3600;
+
+// how far `grow_escrow` may take the escrow account past the current layout
+pub const MAX_ESCROW_GROWTH: usize = 1024;
+
+// bounds the sha256 work in `verify_message`
+pub const MAX_PREIMAGE_LEN: usize = 1024;
+
+// size of the `recent_senders` ring buffer
+pub const RECENT_SENDERS: usize = 8;
+
+// size of the `recent_fees` ring buffer
+pub const RECENT_FEES: usize = 16;
+
+// bounds the compute of `submit_messages_batch`
+pub const MAX_BATCH_SUBMISSIONS: usize = 10;
+
+// What the submit guards look at beyond `Config` and `Escrow`. `participant`
+// is the payer's PDA (`None`, or zeroed, for a wallet that has never played)
+// and `deposit` is whether the call is `submit_deposit`.
+struct SubmitAttempt<'a> {
+    participant: Option<&'a Participant>,
+    client_nonce: Option<u64>,
+    vault_lamports: u64,
+    deposit: bool,
+    now: i64,
+    slot: u64,
+}
+
+// first guard a submission would trip before its fee is looked at, as a
+// `submit_reason` code. Every submit path and `can_submit` go through this;
+// the fee itself is `Escrow::fee_status`.
+fn submission_status(config: &Config, escrow: &Escrow, attempt: &SubmitAttempt) -> u8 {
+    let duplicate = attempt.client_nonce.is_some()
+        && attempt.participant.and_then(|p| p.last_client_nonce) == attempt.client_nonce;
+
+    if duplicate {
+        submit_reason::DUPLICATE_SUBMIT
+    } else if config.global_paused {
+        submit_reason::GLOBALLY_PAUSED
+    } else if escrow.deposit_mode != attempt.deposit {
+        submit_reason::DEPOSIT_MODE
+    } else if escrow.ended {
+        submit_reason::GAME_ENDED
+    } else if escrow.deadline_reached
+        || (escrow.timer_active && !escrow.before_submission_deadline(attempt.now))
+    {
+        // once `latch_deadline` has seen the deadline pass, a clock that
+        // reads earlier must not reopen the game
+        submit_reason::TIMER_EXPIRED
+    } else if escrow.in_settlement_window(attempt.now) {
+        submit_reason::SETTLEMENT_WINDOW
+    } else if attempt.vault_lamports == 0 {
+        // `init_escrow` leaves the vault rent-funded; an empty one means
+        // initialization never finished
+        submit_reason::VAULT_NOT_INITIALIZED
+    } else if escrow.participant_limit_reached(attempt.participant) {
+        submit_reason::PARTICIPANT_LIMIT
+    } else if escrow.slot_full(attempt.slot) {
+        submit_reason::SLOT_LIMIT
+    } else {
+        submit_reason::ELIGIBLE
+    }
+}
+
+// Pyth pull-oracle receiver program; owns every `PriceUpdateV2` account
+pub const PYTH_RECEIVER_ID: Pubkey =
+    anchor_lang::solana_program::pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+// oldest oracle price `submit_message_usd` will convert with, in seconds
+pub const MAX_PRICE_AGE: i64 = 60;
+
+// widest confidence interval accepted, relative to the price, in bps
+pub const MAX_PRICE_CONF_BPS: u64 = 200;
+
+/// SOL/USD price as published by the oracle: `price * 10^exponent` USD per SOL,
+/// give or take `conf` in the same units.
+pub struct OraclePrice {
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+}
+
+// Reads a Pyth `PriceUpdateV2` by hand (the receiver SDK pins an older
+// solana-program). Layout: 8-byte discriminator, write authority, borsh
+// `VerificationLevel` (`Partial { num_signatures }` = 0 + u8, `Full` = 1), then
+// the price message: feed id, price, conf, exponent, publish time, ...
+// Only fully verified updates are accepted.
+fn read_oracle_price(account: &AccountInfo, now: i64) -> Result<OraclePrice> {
+    require_keys_eq!(*account.owner, PYTH_RECEIVER_ID, ErrorCode::OracleMismatch);
+
+    let data = account.try_borrow_data()?;
+    let discriminator = &hash(b"account:PriceUpdateV2").to_bytes()[..8];
+    require!(
+        data.len() >= 8 + 32 + 1 + 32 + 8 + 8 + 4 + 8 && &data[..8] == discriminator,
+        ErrorCode::OracleMismatch
+    );
+    require!(data[40] == 1, ErrorCode::OracleMismatch);
+
+    let message = &data[41 + 32..];
+    let price = OraclePrice {
+        price: i64::from_le_bytes(message[0..8].try_into().unwrap()),
+        conf: u64::from_le_bytes(message[8..16].try_into().unwrap()),
+        exponent: i32::from_le_bytes(message[16..20].try_into().unwrap()),
+        publish_time: i64::from_le_bytes(message[20..28].try_into().unwrap()),
+    };
+
+    require!(
+        now.saturating_sub(price.publish_time) <= MAX_PRICE_AGE,
+        ErrorCode::OraclePriceStale
+    );
+    require!(price.price > 0, ErrorCode::OraclePriceUncertain);
+    // conf / price <= MAX_PRICE_CONF_BPS / 10000
+    require!(
+        (price.conf as u128) * 10_000 <= (price.price as u128) * MAX_PRICE_CONF_BPS as u128,
+        ErrorCode::OraclePriceUncertain
+    );
+
+    Ok(price)
+}
+
+// lamports = cents / 100 * 10^9 / (price * 10^exponent), rounded up so the pot
+// never receives less than the USD fee
+fn usd_cents_to_lamports(cents: u64, price: &OraclePrice) -> Result<u64> {
+    require!((-18..=0).contains(&price.exponent), ErrorCode::OraclePriceUncertain);
+
+    let numerator = (cents as u128)
+        .checked_mul(10_000_000)
+        .and_then(|n| n.checked_mul(10u128.pow(price.exponent.unsigned_abs())))
+        .ok_or(ErrorCode::BadParams)?;
+    let lamports = numerator.div_ceil(price.price as u128);
+
+    u64::try_from(lamports).map_err(|_| error!(ErrorCode::BadParams))
+}
+
+// Lamports moved by the instruction just before the current one, which has to
+// be a plain system-program transfer from `payer` to `vault`. `instructions`
+// is the instructions sysvar.
+fn precredited_transfer(instructions: &AccountInfo, payer: &Pubkey, vault: &Pubkey) -> Result<u64> {
+    require_keys_eq!(instructions.key(), sysvar_instructions::ID, ErrorCode::PrecreditMissing);
+    let index = sysvar_instructions::load_current_index_checked(instructions)?;
+    require!(index > 0, ErrorCode::PrecreditMissing);
+    let ix = sysvar_instructions::load_instruction_at_checked(index as usize - 1, instructions)?;
+
+    // `SystemInstruction::Transfer`: u32 variant 2, then the u64 lamports
+    require_keys_eq!(ix.program_id, system_program::ID, ErrorCode::PrecreditMissing);
+    require!(
+        ix.data.len() == 12 && ix.data[..4] == 2u32.to_le_bytes(),
+        ErrorCode::PrecreditMissing
+    );
+    require!(
+        ix.accounts.len() >= 2 && ix.accounts[0].pubkey == *payer && ix.accounts[1].pubkey == *vault,
+        ErrorCode::PrecreditMissing
+    );
+
+    Ok(u64::from_le_bytes(ix.data[4..12].try_into().unwrap()))
+}
+
+// Metaplex token metadata program; owns every metadata account
+pub const TOKEN_METADATA_ID: Pubkey =
+    anchor_lang::solana_program::pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+// Checks that `token_account` is an SPL token account owned by `holder` with at
+// least one token, and that `metadata` is the Metaplex metadata PDA of its mint
+// naming `collection` either as a verified collection or a verified creator.
+fn require_nft_holder(
+    token_account: &AccountInfo,
+    metadata: &AccountInfo,
+    holder: &Pubkey,
+    collection: &Pubkey,
+) -> Result<()> {
+    require_keys_eq!(*token_account.owner, token::ID, ErrorCode::NftGateFailed);
+    let token = TokenAccount::try_deserialize(&mut &token_account.try_borrow_data()?[..])
+        .map_err(|_| error!(ErrorCode::NftGateFailed))?;
+    require_keys_eq!(token.owner, *holder, ErrorCode::NftGateFailed);
+    require!(token.amount >= 1, ErrorCode::NftGateFailed);
+
+    require_keys_eq!(*metadata.owner, TOKEN_METADATA_ID, ErrorCode::NftGateFailed);
+    let (expected, _) = Pubkey::find_program_address(
+        &[b"metadata", TOKEN_METADATA_ID.as_ref(), token.mint.as_ref()],
+        &TOKEN_METADATA_ID,
+    );
+    require_keys_eq!(metadata.key(), expected, ErrorCode::NftGateFailed);
+
+    let data = metadata.try_borrow_data()?;
+    require!(
+        metadata_names_collection(&data, collection) == Some(true),
+        ErrorCode::NftGateFailed
+    );
+
+    Ok(())
+}
+
+// Walks the borsh layout of a `MetadataV1` account far enough to reach the
+// creators and collection. `None` means the account didn't parse.
+//
+//   key: u8 (4 = MetadataV1), update_authority, mint,
+//   name/symbol/uri: String, seller_fee_basis_points: u16,
+//   creators: Option<Vec<{ address, verified: bool, share: u8 }>>,
+//   primary_sale_happened: bool, is_mutable: bool,
+//   edition_nonce: Option<u8>, token_standard: Option<u8>,
+//   collection: Option<{ verified: bool, key }>, ...
+fn metadata_names_collection(data: &[u8], collection: &Pubkey) -> Option<bool> {
+    fn take<'a>(data: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+        if data.len() < n {
+            return None;
+        }
+        let (head, rest) = data.split_at(n);
+        *data = rest;
+        Some(head)
+    }
+    fn take_u32(data: &mut &[u8]) -> Option<usize> {
+        Some(u32::from_le_bytes(take(data, 4)?.try_into().ok()?) as usize)
+    }
+    fn take_option_u8(data: &mut &[u8]) -> Option<()> {
+        if take(data, 1)?[0] == 1 {
+            take(data, 1)?;
+        }
+        Some(())
+    }
+
+    let mut data = data;
+    if take(&mut data, 1)?[0] != 4 {
+        return None;
+    }
+    take(&mut data, 32 + 32)?;
+    for _ in 0..3 {
+        let len = take_u32(&mut data)?;
+        take(&mut data, len)?;
+    }
+    take(&mut data, 2)?;
+
+    let mut verified_creator = false;
+    if take(&mut data, 1)?[0] == 1 {
+        for _ in 0..take_u32(&mut data)? {
+            let creator = take(&mut data, 34)?;
+            verified_creator |= &creator[..32] == collection.as_ref() && creator[32] == 1;
+        }
+    }
+
+    take(&mut data, 2)?;
+    take_option_u8(&mut data)?;
+    take_option_u8(&mut data)?;
+
+    let mut verified_collection = false;
+    if take(&mut data, 1)?[0] == 1 {
+        let entry = take(&mut data, 33)?;
+        verified_collection = entry[0] == 1 && &entry[1..] == collection.as_ref();
+    }
+
+    Some(verified_creator || verified_collection)
+}
+
+// fixed size of the marketing splitter stored on `Escrow`
+pub const MAX_MARKETING_SPLITS: usize = 4;
+
+// ceiling on the combined marketing skim (25%), inclusive. Every path that
+// sets `marketing_bps` enforces it, which is also what keeps `split_fee`'s
+// prize leg from underflowing.
+pub const MAX_MARKETING_BPS: u16 = 2500;
+
+// most wallets `claim_prize_split` can share a prize between
+pub const MAX_PRIZE_SPLITS: usize = 8;
+
+/// One `claim_prize_split` recipient and its share of the prize, in bps.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct PrizeSplit {
+    pub wallet: Pubkey,
+    pub bps: u16,
+}
+
+fn validate_prize_splits(recipients: &[PrizeSplit]) -> Result<()> {
+    require!(
+        !recipients.is_empty() && recipients.len() <= MAX_PRIZE_SPLITS,
+        ErrorCode::BadParams
+    );
+
+    let mut total_bps: u16 = 0;
+    for recipient in recipients {
+        require!(recipient.bps > 0, ErrorCode::BadParams);
+        total_bps = total_bps
+            .checked_add(recipient.bps)
+            .ok_or(ErrorCode::BpsTooHigh)?;
+    }
+    require!(total_bps == 10_000, ErrorCode::BadParams);
+
+    Ok(())
+}
+
+/// One marketing recipient and its share of each fee, in bps.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MarketingSplit {
+    pub wallet: Pubkey,
+    pub bps: u16,
+}
+
+impl MarketingSplit {
+    pub const LEN: usize = 32 + 2;
+}
+
+// Marketing wallets have to be regular (on-curve) wallets unless the authority
+// opted into PDAs with `marketing_is_pda`, so an address nobody can sign for
+// is caught when it's set rather than noticed later. Crediting a PDA through
+// a system transfer works the same as any other account, so the transfer
+// path itself doesn't change.
+fn require_marketing_wallet(wallet: &Pubkey, allow_pda: bool) -> Result<()> {
+    if *wallet != Pubkey::default() && !allow_pda {
+        require!(wallet.is_on_curve(), ErrorCode::InvalidMarketingWallet);
+    }
+    Ok(())
+}
+
+// Checks a full recipient list and returns the combined bps. The combined
+// share is held to the same 25% cap as a single `marketing_bps`.
+fn validate_marketing_splits(recipients: &[MarketingSplit]) -> Result<u16> {
+    require!(
+        !recipients.is_empty() && recipients.len() <= MAX_MARKETING_SPLITS,
+        ErrorCode::BadParams
+    );
+
+    let mut total_bps: u16 = 0;
+    for (i, recipient) in recipients.iter().enumerate() {
+        require!(
+            recipient.wallet != Pubkey::default() && recipient.bps > 0,
+            ErrorCode::BadParams
+        );
+        require!(
+            recipients[..i].iter().all(|r| r.wallet != recipient.wallet),
+            ErrorCode::BadParams
+        );
+        total_bps = total_bps
+            .checked_add(recipient.bps)
+            .ok_or(ErrorCode::BpsTooHigh)?;
+    }
+    require!(total_bps <= MAX_MARKETING_BPS, ErrorCode::BpsTooHigh);
+
+    Ok(total_bps)
+}
+
+// `init` on `escrow` already refuses a second `initialize`; this covers the
+// vault, which `create_account` would otherwise reject with a bare
+// system-program error if it already holds lamports
+fn require_fresh_vault(vault: &AccountInfo) -> Result<()> {
+    require!(
+        vault.lamports() == 0 && vault.data_is_empty(),
+        ErrorCode::AlreadyInitialized
+    );
+    Ok(())
+}
+
+// shared body of `initialize` / `initialize_with_splits`; `recipients[0]` is
+// the `marketing_wallet` account and `marketing_bps` their combined share
+fn init_escrow(
+    ctx: Context<Initialize>,
+    base_fee: u64,
+    fee_cap: u64,
+    marketing_bps: u16,
+    recipients: &[MarketingSplit],
+) -> Result<()> {
+    // `initialize_with_splits` has already summed and checked these, but
+    // plain `initialize` takes `marketing_bps` straight from the caller
+    require!(marketing_bps <= MAX_MARKETING_BPS, ErrorCode::BpsTooHigh);
+    for recipient in recipients {
+        require_marketing_wallet(&recipient.wallet, false)?;
+        ctx.accounts
+            .config
+            .require_distinct_marketing_wallet(&ctx.accounts.authority.key(), &recipient.wallet)?;
+    }
+
+    let escrow = &mut ctx.accounts.escrow;
+
+    // -------------------------------------------------
+    // 1. Create the vault PDA account manually
+    // -------------------------------------------------
+
+    require_fresh_vault(&ctx.accounts.escrow_vault.to_account_info())?;
+
+    // how much rent-exempt lamports for an account with 0 data bytes
+    let rent_lamports = Rent::get()?.minimum_balance(0);
+
+    // bump for vault PDA
+    let vault_bump = ctx.bumps.escrow_vault;
+
+    // seeds we will sign with for the new account
+    let escrow_seed: &[u8] = b"escrow";
+    let vault_seed: &[u8] = b"vault";
+    let bump_seed: &[u8] = &[vault_bump];
+    let signer_seeds: &[&[u8]] = &[escrow_seed, vault_seed, bump_seed];
+
+    // build the `create_account` ix:
+    // - `authority` funds it
+    // - new account is `escrow_vault`
+    // - owner is this program (crate::ID)
+    invoke_signed(
+        &system_instruction::create_account(
+            &ctx.accounts.authority.key(),               // from
+            &ctx.accounts.escrow_vault.key(),            // new account pubkey (the PDA)
+            rent_lamports,                               // lamports
+            0,                                           // space in bytes
+            &system_program::ID,                                  // owner = this program
+        ),
+        &[
+            ctx.accounts.authority.to_account_info(),
+            ctx.accounts.escrow_vault.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        ],
+        &[signer_seeds],
+    )?;
+
+    // -------------------------------------------------
+    // 2. Initialize escrow state
+    // -------------------------------------------------
+    escrow.authority = ctx.accounts.authority.key();
+    escrow.base_fee = base_fee;
+    escrow.fee_cap = fee_cap;
+    escrow.current_fee = base_fee;
+    escrow.marketing_wallet = ctx.accounts.marketing_wallet.key();
+    escrow.marketing_bps = marketing_bps;
+    escrow.marketing_splits = [MarketingSplit::default(); MAX_MARKETING_SPLITS];
+    escrow.marketing_splits[..recipients.len()].copy_from_slice(recipients);
+    escrow.messages_count = 0;
+    escrow.last_sender = Pubkey::default();
+    escrow.timer_active = false;
+    escrow.deadline = 0;
+    escrow.ended = false;
+    escrow.bump = ctx.bumps.escrow;
+    escrow.first_message_ts = 0;
+    escrow.max_total_duration = 0;
+    escrow.last_known_vault_balance = rent_lamports;
+    escrow.guaranteed_min_prize = 0;
+    escrow.ended_ts = 0;
+    escrow.restart_cooldown = 0;
+    escrow.endgame_waive_marketing = false;
+    escrow.min_fee_increment = 0;
+    escrow.max_fee_paid = 0;
+    escrow.bonus_bps = 0;
+    escrow.recent_senders = [Pubkey::default(); RECENT_SENDERS];
+    escrow.recent_senders_head = 0;
+    escrow.deadline_skew_tolerance = 0;
+    escrow.fee_usd_cents = 0;
+    escrow.oracle = Pubkey::default();
+    escrow.game_number = 0;
+    escrow.unique_participants = 0;
+    escrow.strict_deadline = false;
+    escrow.anti_snipe_window = 0;
+    escrow.anti_snipe_extension = 0;
+    escrow.winning_msg_hash = [0; 32];
+    escrow.strict_accounting = false;
+    escrow.accounted_balance = 0;
+    escrow.params_locked_after = 0;
+    escrow.required_collection = Pubkey::default();
+    escrow.staking_bps = 0;
+    escrow.marketing_is_pda = false;
+    escrow.recent_fees = [0; RECENT_FEES];
+    escrow.recent_fees_head = 0;
+    escrow.self_submit_cooldown = 0;
+    escrow.self_submit_penalty_bps = 0;
+    escrow.max_single_fee = u64::MAX;
+    escrow.max_extensions = 0;
+    escrow.extension_count = 0;
+    escrow.min_unique_to_claim = 1;
+    escrow.winner_rebate_bps = 0;
+    escrow.claim_delay_after_deadline = 0;
+    escrow.last_submit_ts = 0;
+    escrow.hold_reward_interval = 0;
+    escrow.hold_reward_bps = 0;
+    escrow.deadline_reached = false;
+    escrow.scheduled_end = false;
+    escrow.last_slot = 0;
+    escrow.submissions_this_slot = 0;
+    escrow.max_submissions_per_slot = u64::MAX;
+    escrow.total_marketing_paid = 0;
+    escrow.marketing_cap = u64::MAX;
+    escrow.marketing_min = 0;
+    escrow.extensions_frozen = false;
+    escrow.leader_since = 0;
+    escrow.marketing_mode = marketing_mode::PER_SUBMISSION;
+    escrow.sniped_refund_bps = 0;
+    escrow.sniped_wallet = Pubkey::default();
+    escrow.sniped_refund = 0;
+    escrow.last_prize_fee = 0;
+    escrow.min_fee_paid = 0;
+    escrow.min_unique_for_extension = 0;
+    escrow.gas_reserve = 0;
+    escrow.total_fees_collected = 0;
+    escrow.total_prize_paid = 0;
+    escrow.param_change_cooldown = 0;
+    escrow.last_param_change_ts = 0;
+    escrow.post_deadline_decay_bps_per_hour = 0;
+    escrow.decay_base = 0;
+    escrow.decay_burned = 0;
+    escrow.prevent_self_deal = false;
+    escrow.max_pot = u64::MAX;
+    escrow.settlement_buffer = 0;
+    escrow.deposit_mode = false;
+    escrow.deposits_outstanding = 0;
+    escrow.leader_deposit = 0;
+    escrow.max_participants = u64::MAX;
+
+    Ok(())
+}
+
+// steps 7-9 of `submit_message`, shared by every path that accepts a message
+// once the fee has landed
+// `prize_fee` is the part of `fee_paid` that reached the vault as prize
+fn record_submission(
+    escrow: &mut Escrow,
+    sender: Pubkey,
+    msg_hash: [u8; 32],
+    fee_paid: u64,
+    marketing_fee: u64,
+    prize_fee: u64,
+    now: i64,
+) -> Result<()> {
+    // at most `max_submissions_per_slot` across all payers, so a burst of
+    // wallets in one slot can't ratchet the fee up faster than that
+    let slot = Clock::get()?.slot;
+    let prev_slot = escrow.last_slot;
+    require!(!escrow.slot_full(slot), ErrorCode::SlotSubmissionLimit);
+    if slot != escrow.last_slot {
+        escrow.last_slot = slot;
+        escrow.submissions_this_slot = 0;
+    }
+    escrow.submissions_this_slot += 1;
+
+    // -------------------------------------------------
+    // 7. update on-chain state
+    // -------------------------------------------------
+    if escrow.messages_count == 0 {
+        escrow.first_message_ts = now;
+    }
+    escrow.messages_count = escrow.messages_count.checked_add(1).unwrap();
+    // a leader resubmitting keeps their original `leader_since`
+    if escrow.last_sender != sender {
+        escrow.leader_since = now;
+        escrow.note_snipe(slot, prev_slot);
+    }
+    escrow.last_sender = sender;
+    escrow.last_submit_ts = now;
+    escrow.winning_msg_hash = msg_hash;
+    escrow.max_fee_paid = escrow.max_fee_paid.max(fee_paid);
+    escrow.total_fees_collected = escrow.total_fees_collected.checked_add(fee_paid).unwrap();
+    escrow.recent_senders[escrow.recent_senders_head as usize] = sender;
+    escrow.recent_senders_head = (escrow.recent_senders_head + 1) % RECENT_SENDERS as u8;
+    escrow.recent_fees[escrow.recent_fees_head as usize] = fee_paid;
+    escrow.recent_fees_head = (escrow.recent_fees_head + 1) % RECENT_FEES as u8;
+    escrow.last_prize_fee = prize_fee;
+
+    // timer rules
+    let mut timer_started = false;
+    let mut timer_extended = false;
+
+    if !escrow.timer_active && escrow.messages_count >= START_AFTER {
+        escrow.timer_active = true;
+        escrow.deadline = escrow.next_deadline(now);
+        timer_started = true;
+    } else if escrow.timer_active && escrow.before_submission_deadline(now) {
+        if escrow.extension_gated() {
+            emit!(ExtensionWithheld {
+                unique_participants: escrow.unique_participants,
+                required: escrow.min_unique_for_extension,
+            });
+        }
+        if let Some(deadline) = escrow.extended_deadline(now) {
+            escrow.deadline = deadline;
+            escrow.deadline_reached = false;
+            escrow.extension_count = escrow.extension_count.checked_add(1).unwrap();
+            timer_extended = true;
+        }
+    }
+
+    // -------------------------------------------------
+    // 8. bump the dynamic fee, capped
+    // -------------------------------------------------
+    let prev_fee = escrow.current_fee;
+    escrow.current_fee = escrow.next_fee();
+    // no marketing wallet means the split's skim is never sent
+    let marketing_amount = if escrow.marketing_wallet == Pubkey::default() {
+        0
+    } else {
+        marketing_fee
+    };
+
+    // -------------------------------------------------
+    // 9. emit events
+    // -------------------------------------------------
+    emit!(MessageSubmitted {
+        sender,
+        msg_hash,
+        fee_paid,
+        new_fee: escrow.current_fee,
+        fee_step: escrow.current_fee.saturating_sub(prev_fee),
+        timestamp: now,
+        submissions_this_slot: escrow.submissions_this_slot,
+        marketing_applied: marketing_amount > 0,
+        marketing_amount,
+    });
+
+    if timer_started {
+        emit!(TimerStarted {
+            deadline: escrow.deadline,
+        });
+    } else if timer_extended {
+        emit!(TimerExtended {
+            new_deadline: escrow.deadline,
+            anti_snipe: escrow.anti_snipe_window > 0,
+            extensions_remaining: escrow.extensions_remaining(),
+        });
+    }
+
+    Ok(())
+}
+
+// Per-wallet bookkeeping for every path that has the payer's `Participant`
+// PDA. Runs `join_game` first, so callers that already did are unaffected.
+fn track_participant(
+    escrow: &mut Escrow,
+    participant: &mut Participant,
+    wallet: Pubkey,
+    bump: u8,
+    messages: u64,
+    fee_paid: u64,
+    now: i64,
+) {
+    join_game(escrow, participant, wallet, bump, now);
+
+    participant.messages_count = participant.messages_count.checked_add(messages).unwrap();
+    participant.contributed = participant.contributed.checked_add(fee_paid).unwrap();
+    participant.last_submit_ts = now;
+}
+
+// The per-game counters restart the first time a wallet plays in a new
+// `game_number`, which is also when it counts toward `unique_participants`.
+// Submit paths call this before `record_submission`, so the submission that
+// brings in a new wallet already counts toward `min_unique_for_extension`.
+fn join_game(
+    escrow: &mut Escrow,
+    participant: &mut Participant,
+    wallet: Pubkey,
+    bump: u8,
+    now: i64,
+) {
+    if participant.wallet == Pubkey::default() {
+        // created by `init_if_needed` in this instruction
+        participant.wallet = wallet;
+        participant.bump = bump;
+        participant.first_ts = now;
+        participant.game_number = escrow.game_number;
+        escrow.unique_participants = escrow.unique_participants.checked_add(1).unwrap();
+    } else if participant.game_number != escrow.game_number {
+        participant.game_number = escrow.game_number;
+        participant.messages_count = 0;
+        participant.contributed = 0;
+        escrow.unique_participants = escrow.unique_participants.checked_add(1).unwrap();
+    }
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Escrow::LEN,
+        seeds = [b"escrow"],
+        bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    /// CHECK:
+    /// This PDA will be created in this instruction via `create_account`
+    /// and owned by this program. We don't read or trust any preexisting data.
+    #[account(
+        mut,
+        seeds = [b"escrow", b"vault"],
+        bump
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    /// CHECK: arbitrary marketing wallet set by the authority; not controlled by program
+    pub marketing_wallet: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitMessage<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    /// CHECK:
+    /// This is the vault PDA (seeds ["escrow","vault"]) created in `initialize`.
+    /// It is owned by our program (not the system program) and just holds lamports.
+    /// We only ever move lamports via CPI using invoke_signed(), so we trust seeds+bump
+    /// instead of Anchor's owner check.
+    #[account(
+        mut,
+        seeds = [b"escrow", b"vault"],
+        bump
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    /// CHECK:
+    /// This is the marketing wallet set by the authority; not controlled by program
+    /// Must be the wallet stored in escrow
+    #[account(
+        mut,
+        address = escrow.marketing_wallet @ ErrorCode::Unauthorized
+    )]
+    pub marketing_wallet: UncheckedAccount<'info>,
+
+    /// Payer's per-wallet state, created (at the payer's expense) on their
+    /// first submission.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Participant::LEN,
+        seeds = [b"participant", payer.key().as_ref()],
+        bump
+    )]
+    pub participant: Account<'info, Participant>,
+
+    /// CHECK:
+    /// Staking rewards PDA `[b"staking"]`; receives `staking_bps` of each fee
+    /// out of the marketing share. Only the program derives it.
+    #[account(
+        mut,
+        seeds = [b"staking"],
+        bump
+    )]
+    pub staking_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SubmitMessage<'info> {
+    // Steps 4-9 of `submit_message` for the variant paths that work out
+    // `fee_paid` themselves: split, transfer, then record the message. With
+    // `vault_bump` the fee is already in the vault; see `transfer_fees`.
+    fn accept_fee(
+        &mut self,
+        msg_hash: [u8; 32],
+        fee_paid: u64,
+        extra_recipients: &[AccountInfo<'info>],
+        participant_bump: u8,
+        now: i64,
+        vault_bump: Option<u8>,
+    ) -> Result<()> {
+        self.escrow.require_fee_in_bounds(fee_paid)?;
+
+        let (marketing_fee, prize_fee) = self.escrow.split_fee(fee_paid);
+        self.escrow.emit_marketing_waived(fee_paid);
+        self.escrow.emit_marketing_floor(fee_paid);
+        let penalty = self.escrow.self_submit_penalty(&self.participant, fee_paid, now);
+        let prize_fee = prize_fee.checked_add(penalty).unwrap();
+        if penalty > 0 {
+            emit!(SelfSubmitPenalty { amount: penalty });
+        }
+
+        let prize_fee =
+            self.transfer_fees(prize_fee, marketing_fee, extra_recipients, vault_bump)?;
+
+        let escrow = &mut self.escrow;
+        escrow.add_marketing_paid(marketing_fee);
+        escrow.last_known_vault_balance = self.escrow_vault.lamports();
+        escrow.accounted_balance = escrow.accounted_balance.checked_add(prize_fee).unwrap();
+
+        let payer = self.payer.key();
+        join_game(escrow, &mut self.participant, payer, participant_bump, now);
+        record_submission(escrow, payer, msg_hash, fee_paid, marketing_fee, prize_fee, now)?;
+        track_participant(
+            escrow,
+            &mut self.participant,
+            payer,
+            participant_bump,
+            1,
+            fee_paid + penalty,
+            now,
+        );
+
+        Ok(())
+    }
+
+    // `submission_status` for this payer and vault
+    fn submission_status(&self, now: i64, deposit: bool, client_nonce: Option<u64>) -> Result<u8> {
+        let attempt = SubmitAttempt {
+            participant: Some(&self.participant),
+            client_nonce,
+            vault_lamports: self.escrow_vault.lamports(),
+            deposit,
+            now,
+            slot: Clock::get()?.slot,
+        };
+        Ok(submission_status(&self.config, &self.escrow, &attempt))
+    }
+
+    // guards every submission path runs before taking a fee
+    fn require_accepting(&self, now: i64, deposit: bool) -> Result<()> {
+        let reason = self.submission_status(now, deposit, None)?;
+        require!(reason == submit_reason::ELIGIBLE, submit_error(reason));
+        Ok(())
+    }
+
+    // steps 5-6 of `submit_message`. Extra marketing recipients (splits after
+    // the first) are passed as writable `remaining_accounts`, in split order.
+    // Returns the part of `prize_fee` that counts toward the pot; any excess
+    // over `max_pot` goes to the primary marketing wallet instead.
+    //
+    // With `vault_bump` the whole fee was precredited to the vault: the prize
+    // leg is already in place and every other leg is paid out of the vault,
+    // signed with that bump, rather than by the payer.
+    fn transfer_fees(
+        &self,
+        prize_fee: u64,
+        marketing_fee: u64,
+        extra_recipients: &[AccountInfo<'info>],
+        vault_bump: Option<u8>,
+    ) -> Result<u64> {
+        // a leg "to" the payer only skips its CPI when the payer is paying it;
+        // out of the vault it's a real transfer
+        let from_payer = vault_bump.is_none();
+        let payer_is = |wallet: Pubkey| from_payer && wallet == self.payer.key();
+
+        // -------------------------------------------------
+        // 5. payer -> escrow_vault (the prize pool)
+        // -------------------------------------------------
+        // a precredited fee is already in the vault, so measure the pot as it
+        // was before the credit
+        let vault_before = match vault_bump {
+            Some(_) => self.escrow.last_known_vault_balance,
+            None => self.escrow_vault.lamports(),
+        };
+        let excess = self.escrow.pot_excess(prize_fee, vault_before);
+        let prize_fee = prize_fee - excess;
+        // the excess is charged whoever pays. With no marketing wallet to take
+        // it, or the payer being that wallet, it goes into the vault with the
+        // prize leg but stays out of the pot: `pay_out` never pays past
+        // `max_pot`, so it's left over for the next game.
+        let mut vault_fee = prize_fee;
+        if excess > 0 {
+            if self.escrow.marketing_wallet != Pubkey::default()
+                && !payer_is(self.marketing_wallet.key())
+            {
+                self.send_fee(&self.marketing_wallet.to_account_info(), excess, vault_bump)?;
+            } else {
+                vault_fee += excess;
+            }
+            emit!(PotCapped { excess });
+        }
+        if vault_fee > 0 && from_payer {
+            self.send_fee(&self.escrow_vault.to_account_info(), vault_fee, None)?;
+        }
+
+        // -------------------------------------------------
+        // 6. payer -> marketing_wallet (the rake)
+        // -------------------------------------------------
+        if marketing_fee > 0 && self.escrow.marketing_wallet != Pubkey::default() {
+            // the staking pool's sub-split comes off the top of the rake
+            let staking_fee = self.escrow.staking_cut(marketing_fee);
+            if staking_fee > 0 {
+                self.send_fee(&self.staking_vault.to_account_info(), staking_fee, vault_bump)?;
+                emit!(StakingFeeSent { amount: staking_fee });
+            }
+            let marketing_fee = marketing_fee - staking_fee;
+
+            let mut primary_fee = marketing_fee;
+            let extra_splits = self.escrow.marketing_splits[1..]
+                .iter()
+                .filter(|split| split.bps > 0);
+            let mut extra_accounts = extra_recipients.iter();
+
+            for split in extra_splits {
+                let recipient = extra_accounts
+                    .next()
+                    .ok_or(ErrorCode::MarketingRecipientMismatch)?;
+                require_keys_eq!(
+                    recipient.key(),
+                    split.wallet,
+                    ErrorCode::MarketingRecipientMismatch
+                );
+
+                // each recipient's share of the skim is weighted by its bps
+                let share = (marketing_fee as u128)
+                    .checked_mul(split.bps as u128)
+                    .unwrap()
+                    .checked_div(self.escrow.marketing_bps as u128)
+                    .unwrap() as u64;
+                primary_fee = primary_fee.checked_sub(share).unwrap();
+
+                if share > 0 && payer_is(split.wallet) {
+                    emit!(MarketingSelfTransferSkipped { wallet: split.wallet, amount: share });
+                } else if share > 0 {
+                    self.send_fee(recipient, share, vault_bump)?;
+                    emit!(MarketingFeeSent { wallet: split.wallet, amount: share });
+                }
+            }
+
+            // the first recipient also absorbs the rounding dust
+            let marketing_fee = primary_fee;
+
+            // a payer who is also the marketing wallet would just be paying
+            // themselves; skip the CPI, the lamports already sit where they'd go
+            if payer_is(self.marketing_wallet.key()) {
+                emit!(MarketingSelfTransferSkipped {
+                    wallet: self.marketing_wallet.key(),
+                    amount: marketing_fee,
+                });
+                return Ok(prize_fee);
+            }
+
+            self.send_fee(&self.marketing_wallet.to_account_info(), marketing_fee, vault_bump)?;
+            emit!(MarketingFeeSent { wallet: self.marketing_wallet.key(), amount: marketing_fee });
+        }
+
+        Ok(prize_fee)
+    }
+
+    // One leg of a fee: from the payer, or out of the vault (signed with
+    // `vault_bump`) when the fee was precredited there.
+    fn send_fee(&self, to: &AccountInfo<'info>, amount: u64, vault_bump: Option<u8>) -> Result<()> {
+        match vault_bump {
+            Some(bump) => vault_transfer(
+                &self.escrow_vault.to_account_info(),
+                bump,
+                to,
+                &self.system_program.to_account_info(),
+                amount,
+            ),
+            None => {
+                invoke(
+                    &system_instruction::transfer(&self.payer.key(), &to.key(), amount),
+                    &[
+                        self.payer.to_account_info(),
+                        to.clone(),
+                        self.system_program.to_account_info(),
+                    ],
+                )?;
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Accounts)]
+pub struct ClaimPrize<'info> {
+    #[account(mut)]
+    pub winner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    /// CHECK:
+    /// Program-owned vault PDA that holds the pooled lamports.
+    /// We'll sign for it with [b"escrow", b"vault", bump] and transfer out all lamports.
+    #[account(
+        mut,
+        seeds = [b"escrow", b"vault"],
+        bump
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    /// CHECK:
+    /// Sponsor PDA `[b"escrow", b"sponsor"]`. Anyone can pre-fund it with a plain
+    /// transfer; only the program can sign lamports out of it, and only to cover
+    /// a `guaranteed_min_prize` shortfall at payout.
+    #[account(
+        mut,
+        seeds = [b"escrow", b"sponsor"],
+        bump
+    )]
+    pub sponsor_vault: SystemAccount<'info>,
+
+    /// CHECK:
+    /// Recent sender picked for the bonus (see `Escrow::bonus_index`). Only needed
+    /// when `bonus_bps > 0`; its key is checked against `recent_senders`.
+    #[account(mut)]
+    pub bonus_winner: Option<UncheckedAccount<'info>>,
+
+    /// CHECK:
+    /// Platform fee recipient. Only needed when `config.platform_claim_bps > 0`;
+    /// its key is checked against `config.platform_wallet`.
+    #[account(mut)]
+    pub platform_wallet: Option<UncheckedAccount<'info>>,
+
+    /// CHECK:
+    /// Only needed in `marketing_mode::AT_CLAIM`; its key is checked against
+    /// `escrow.marketing_wallet`.
+    #[account(mut)]
+    pub marketing_wallet: Option<UncheckedAccount<'info>>,
+
+    /// The winner's `Participant` PDA; only needed for `winner_rebate_bps`.
+    #[account(
+        seeds = [b"participant", winner.key().as_ref()],
+        bump = winner_participant.bump
+    )]
+    pub winner_participant: Option<Account<'info, Participant>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Config::LEN,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // only the program's upgrade authority may claim the platform admin role,
+    // otherwise anyone could front-run the singleton and own the kill switch
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::TreasuryEscrow>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key()) @ ErrorCode::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetGlobalPaused<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct SetPlatformFee<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct SetCurrentFee<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetGuaranteedPrize<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetRestartCooldown<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct RestartGame<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", b"vault"],
+        bump
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimAndRestart<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    /// `last_sender` (checked by `claim_status`), co-signing to consent to
+    /// `rollover_bps`
+    #[account(mut)]
+    pub winner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", b"vault"],
+        bump
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", b"sponsor"],
+        bump
+    )]
+    pub sponsor_vault: SystemAccount<'info>,
+
+    /// CHECK:
+    /// See `ClaimPrize::bonus_winner`.
+    #[account(mut)]
+    pub bonus_winner: Option<UncheckedAccount<'info>>,
+
+    /// CHECK:
+    /// See `ClaimPrize::platform_wallet`.
+    #[account(mut)]
+    pub platform_wallet: Option<UncheckedAccount<'info>>,
+
+    /// CHECK:
+    /// See `ClaimPrize::marketing_wallet`.
+    #[account(mut)]
+    pub marketing_wallet: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetEndgameWaiveMarketing<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetMinFeeIncrement<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetBonusBps<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetDeadlineSkewTolerance<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetUsdFee<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+#[instruction(additional: u32)]
+pub struct GrowEscrow<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized,
+        realloc = escrow.to_account_info().data_len() + additional as usize,
+        realloc::payer = authority,
+        realloc::zero = true
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetStrictDeadline<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetAntiSnipe<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyMessage<'info> {
+    #[account(
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetStrictAccounting<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SweepUnaccounted<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", b"vault"],
+        bump
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetParamsLockedAfter<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetRequiredCollection<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetStakingBps<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(
+        mut,
+        seeds = [b"staking"],
+        bump
+    )]
+    pub staking_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ResolvePdas {}
+
+#[derive(Accounts)]
+pub struct SetMarketingIsPda<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetSelfSubmitPenalty<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetMaxSingleFee<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetMaxExtensions<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetMinUniqueToClaim<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetWinnerRebateBps<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetClaimDelay<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetHoldReward<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct ScheduleEnd<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetMaxSubmissionsPerSlot<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetMarketingCap<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetMarketingMin<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetExtensionsFrozen<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetMarketingMode<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetSnipedRefundBps<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimSnipedRefund<'info> {
+    #[account(mut)]
+    pub claimant: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", b"vault"],
+        bump
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetMinFeePaid<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetMinUniqueForExtension<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetGasReserve<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetParamChangeCooldown<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetPostDeadlineDecay<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetPreventSelfDeal<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetMaxPot<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetSettlementBuffer<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetDepositMode<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimDeposit<'info> {
+    #[account(mut)]
+    pub claimant: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", b"vault"],
+        bump
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"participant", claimant.key().as_ref()],
+        bump = participant.bump
+    )]
+    pub participant: Account<'info, Participant>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetMaxParticipants<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct ReadRecentSenders<'info> {
+    #[account(
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct ReadRecentFees<'info> {
+    #[account(
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct ReadExtensionsRemaining<'info> {
+    #[account(
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct BreakEvenCheck<'info> {
+    pub payer: Signer<'info>,
+
+    #[account(
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        seeds = [b"escrow", b"vault"],
+        bump
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    /// Omit if the payer has never submitted.
+    #[account(
+        seeds = [b"participant", payer.key().as_ref()],
+        bump = participant.bump
+    )]
+    pub participant: Option<Account<'info, Participant>>,
+}
+
+#[derive(Accounts)]
+pub struct ReadWinnerPayout<'info> {
+    #[account(
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        seeds = [b"escrow", b"vault"],
+        bump
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"escrow", b"sponsor"],
+        bump
+    )]
+    pub sponsor_vault: SystemAccount<'info>,
+
+    /// CHECK: the current leader; only its balance is read, for `gas_reserve`
+    #[account(address = escrow.last_sender)]
+    pub winner: UncheckedAccount<'info>,
+
+    /// The leader's `Participant` PDA; only needed for `winner_rebate_bps`.
+    #[account(
+        seeds = [b"participant", escrow.last_sender.as_ref()],
+        bump = winner_participant.bump
+    )]
+    pub winner_participant: Option<Account<'info, Participant>>,
+}
+
+#[derive(Accounts)]
+pub struct ReadFeePolicy<'info> {
+    #[account(
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct ReadSecurityInfo {}
+
+#[derive(Accounts)]
+pub struct Checkpoint<'info> {
+    #[account(
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(
+        seeds = [b"escrow", b"vault"],
+        bump
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct LatchDeadline<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyDecay<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", b"vault"],
+        bump
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    /// CHECK: the burn address; only ever receives lamports
+    #[account(mut, address = incinerator::ID)]
+    pub incinerator: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AmIAuthority<'info> {
+    pub caller: Signer<'info>,
+
+    #[account(
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct ReadMarketingRecipients<'info> {
+    #[account(
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct ReadParticipation<'info> {
+    #[account(
+        seeds = [b"participant", wallet.as_ref()],
+        bump = participant.bump
+    )]
+    pub participant: Account<'info, Participant>,
+}
+
+#[derive(Accounts)]
+pub struct ReadConsolationList<'info> {
+    #[account(
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct ReadProjectedEnd<'info> {
+    #[account(
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct ReadLeaderInfo<'info> {
+    #[account(
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct CanIClaim<'info> {
+    pub caller: Signer<'info>,
+
+    #[account(
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        seeds = [b"escrow", b"vault"],
+        bump
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReadMyFee<'info> {
+    pub payer: Signer<'info>,
+
+    #[account(
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    /// Omit if the payer has never submitted.
+    #[account(
+        seeds = [b"participant", payer.key().as_ref()],
+        bump = participant.bump
+    )]
+    pub participant: Option<Account<'info, Participant>>,
+}
+
+#[derive(Accounts)]
+pub struct CanSubmit<'info> {
+    pub payer: Signer<'info>,
+
+    #[account(
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(
+        seeds = [b"escrow", b"vault"],
+        bump
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    /// Omit if the payer has never submitted.
+    #[account(
+        seeds = [b"participant", payer.key().as_ref()],
+        bump = participant.bump
+    )]
+    pub participant: Option<Account<'info, Participant>>,
+}
+
+#[derive(Accounts)]
+pub struct ForfeitPrize<'info> {
+    pub winner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        seeds = [b"escrow", b"vault"],
+        bump
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct JigsawApprovePayout<'info> {
+    /// CHECK:
+    /// authority == jigsaw_approver in the `escrow` constraint, so this is safe.
+    pub jigsaw_approver: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == jigsaw_approver.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    /// CHECK:
+    /// `winner` is just the payout destination. We never read or mutate its data,
+    /// we only send lamports to it via a system transfer. So it does not need to
+    /// satisfy any owner/type invariants.
+    #[account(mut)]
+    pub winner: UncheckedAccount<'info>,
+
+    /// CHECK:
+    /// `escrow_vault` is the program-owned PDA `[b"escrow", b"vault"]` that holds
+    /// the prize pool lamports. We sign for it with `invoke_signed` using those seeds.
+    #[account(
+        mut,
+        seeds = [b"escrow", b"vault"],
+        bump
+    )]
+    pub escrow_vault: SystemAccount<'info>,
+
+    /// CHECK:
+    /// Sponsor PDA `[b"escrow", b"sponsor"]`. Anyone can pre-fund it with a plain
+    /// transfer; only the program can sign lamports out of it, and only to cover
+    /// a `guaranteed_min_prize` shortfall at payout.
+    #[account(
+        mut,
+        seeds = [b"escrow", b"sponsor"],
+        bump
+    )]
+    pub sponsor_vault: SystemAccount<'info>,
+
+    /// CHECK:
+    /// Recent sender picked for the bonus (see `Escrow::bonus_index`). Only needed
+    /// when `bonus_bps > 0`; its key is checked against `recent_senders`.
+    #[account(mut)]
+    pub bonus_winner: Option<UncheckedAccount<'info>>,
+
+    /// CHECK:
+    /// Platform fee recipient. Only needed when `config.platform_claim_bps > 0`;
+    /// its key is checked against `config.platform_wallet`.
+    #[account(mut)]
+    pub platform_wallet: Option<UncheckedAccount<'info>>,
+
+    /// CHECK:
+    /// Only needed in `marketing_mode::AT_CLAIM`; its key is checked against
+    /// `escrow.marketing_wallet`.
+    #[account(mut)]
+    pub marketing_wallet: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+
+#[derive(Accounts)]
+pub struct SetFeeParams<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct SetMarketingParams<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+    
+    /// CHECK: New marketing wallet
+    pub marketing_wallet: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMaxTotalDuration<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump = escrow.bump,
+        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[account]
+pub struct Escrow {
+    pub authority: Pubkey,
+    pub base_fee: u64,
+    pub fee_cap: u64,
+    pub current_fee: u64,
+    pub marketing_wallet: Pubkey,
+    pub marketing_bps: u16,
+    pub messages_count: u64,
+    pub last_sender: Pubkey,
+    pub timer_active: bool,
+    pub deadline: i64,
+    pub ended: bool,
+    pub bump: u8,
+    pub first_message_ts: i64,
+    /// hard wall-clock limit measured from `first_message_ts`; 0 = unlimited
+    pub max_total_duration: i64,
+    /// vault balance as of the last instruction that moved lamports through it
+    pub last_known_vault_balance: u64,
+    /// minimum payout promised to the winner, topped up from the sponsor PDA
+    pub guaranteed_min_prize: u64,
+    pub ended_ts: i64,
+    /// minimum gap between `ended_ts` and `restart_game`
+    pub restart_cooldown: i64,
+    /// skip the marketing skim once the timer is active
+    pub endgame_waive_marketing: bool,
+    /// floor on how much each submission raises `current_fee`
+    pub min_fee_increment: u64,
+    /// largest single fee paid this game
+    pub max_fee_paid: u64,
+    /// marketing recipients; the first mirrors `marketing_wallet` and the bps
+    /// sum to `marketing_bps`. Unused slots are zeroed.
+    pub marketing_splits: [MarketingSplit; MAX_MARKETING_SPLITS],
+    /// share of the pot paid to one recent sender at payout
+    pub bonus_bps: u16,
+    /// last `RECENT_SENDERS` senders; `recent_senders_head` is the next write slot
+    pub recent_senders: [Pubkey; RECENT_SENDERS],
+    pub recent_senders_head: u8,
+    /// seconds of slack on both sides of `deadline` to absorb validator clock
+    /// skew; see `claimable_from` / `submission_deadline`
+    pub deadline_skew_tolerance: i64,
+    /// flat fee for `submit_message_usd`, in USD cents; 0 = USD path disabled
+    pub fee_usd_cents: u64,
+    /// SOL/USD `PriceUpdateV2` account `submit_message_usd` must be given
+    pub oracle: Pubkey,
+    /// bumped by `restart_game`; scopes the per-game `Participant` counters
+    pub game_number: u64,
+    /// distinct wallets that have submitted in the current game
+    pub unique_participants: u64,
+    /// reject submissions landing exactly on the (skew-adjusted) deadline
+    pub strict_deadline: bool,
+    /// when > 0, only submissions this close to `deadline` extend it
+    pub anti_snipe_window: i64,
+    /// extension applied by the anti-snipe rule, measured from the submission
+    pub anti_snipe_extension: i64,
+    /// `msg_hash` of `last_sender`'s message, i.e. the sha256 commitment of
+    /// the current leading (eventually winning) message
+    pub winning_msg_hash: [u8; 32],
+    /// pay out only `accounted_balance`, not whatever sits in the vault
+    pub strict_accounting: bool,
+    /// prize lamports that arrived through the fee flow since the last payout
+    pub accounted_balance: u64,
+    /// once `messages_count` exceeds this, `marketing_bps` can only go down;
+    /// 0 = never locked
+    pub params_locked_after: u64,
+    /// collection (or verified creator) `submit_message_nft_gated` requires the
+    /// payer to hold an NFT from
+    pub required_collection: Pubkey,
+    /// part of `marketing_bps` routed to the `[b"staking"]` PDA instead
+    pub staking_bps: u16,
+    /// allow off-curve (PDA) marketing wallets
+    pub marketing_is_pda: bool,
+    /// fees paid by the last `RECENT_FEES` submissions; `recent_fees_head` is
+    /// the next write slot
+    pub recent_fees: [u64; RECENT_FEES],
+    pub recent_fees_head: u8,
+    /// resubmitting within this many seconds of your own last message costs
+    /// `self_submit_penalty_bps` extra, all of it to the pot
+    pub self_submit_cooldown: i64,
+    pub self_submit_penalty_bps: u16,
+    /// hard ceiling on any single charge, separate from `fee_cap`
+    pub max_single_fee: u64,
+    /// cap on timer extensions per game; 0 = unlimited
+    pub max_extensions: u64,
+    pub extension_count: u64,
+    /// distinct wallets a game needs before its pot can be claimed
+    pub min_unique_to_claim: u64,
+    /// share of their own fees refunded to the winner out of the pot at
+    /// `claim_prize`, ahead of the platform fee and bonus
+    pub winner_rebate_bps: u16,
+    /// seconds after the deadline before `claim_prize` opens; 0 = immediately
+    pub claim_delay_after_deadline: i64,
+    /// time of the latest accepted submission, any path
+    pub last_submit_ts: i64,
+    /// leader hold time after which the next fee is discounted; 0 = off
+    pub hold_reward_interval: i64,
+    /// discount off `current_fee` once `hold_reward_interval` has passed
+    pub hold_reward_bps: u16,
+    /// set by `latch_deadline` once `claimable_from` has been observed;
+    /// keeps the claim open even if a later clock reads earlier
+    pub deadline_reached: bool,
+    /// `deadline` was pinned by `schedule_end`; submissions no longer extend it
+    pub scheduled_end: bool,
+    /// slot of the latest accepted submission
+    pub last_slot: u64,
+    /// submissions accepted in `last_slot`
+    pub submissions_this_slot: u64,
+    pub max_submissions_per_slot: u64,
+    /// marketing (staking included) paid out this game
+    pub total_marketing_paid: u64,
+    /// per-game ceiling on `total_marketing_paid`
+    pub marketing_cap: u64,
+    /// floor on each message's marketing skim while `marketing_bps > 0`
+    pub marketing_min: u64,
+    /// submissions are accepted but never extend the deadline
+    pub extensions_frozen: bool,
+    /// when `last_sender` last changed hands
+    pub leader_since: i64,
+    /// a `marketing_mode` value
+    pub marketing_mode: u8,
+    /// share of a sniped leader's fee they can reclaim; 0 = off
+    pub sniped_refund_bps: u16,
+    /// latest leader outbid within a slot of paying, and what they're owed
+    pub sniped_wallet: Pubkey,
+    pub sniped_refund: u64,
+    /// smallest fee a submission may actually be charged
+    pub min_fee_paid: u64,
+    /// distinct players needed before submissions extend the timer
+    pub min_unique_for_extension: u64,
+    /// balance a winner is topped up to out of the pot before other cuts
+    pub gas_reserve: u64,
+    /// fees paid by submissions this game
+    pub total_fees_collected: u64,
+    /// paid to this game's winner, sponsor top-up included
+    pub total_prize_paid: u64,
+    /// minimum seconds between fee/marketing parameter changes
+    pub param_change_cooldown: i64,
+    pub last_param_change_ts: i64,
+    /// share of the pot burned per hour the claim window sits open
+    pub post_deadline_decay_bps_per_hour: u16,
+    /// pot when decay first applied this game, and how much has burned since
+    pub decay_base: u64,
+    pub decay_burned: u64,
+    /// payouts refuse a winner who is the authority or a marketing recipient
+    pub prevent_self_deal: bool,
+    /// largest pot submissions may build and a payout may pay
+    pub max_pot: u64,
+    /// submissions close this many seconds before `deadline`
+    pub settlement_buffer: i64,
+    /// submissions are refundable deposits (`submit_deposit`), not fees
+    pub deposit_mode: bool,
+    /// deposits in the vault that still belong to their payers
+    pub deposits_outstanding: u64,
+    /// `last_sender`'s deposits this game, released into the pot at payout
+    pub leader_deposit: u64,
+    /// once `unique_participants` reaches this, wallets without a
+    /// `Participant` PDA are turned away
+    pub max_participants: u64,
+    /// prize leg the latest submission actually put in the vault; a snipe
+    /// refund is cut from this, not the gross fee
+    pub last_prize_fee: u64,
+}
+
+impl Escrow {
+    pub const LEN: usize = 32 + 8 + 8 + 8 + 32 + 2 + 8 + 32 + 1 + 8 + 1 + 1 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 8 + 8
+        + MarketingSplit::LEN * MAX_MARKETING_SPLITS
+        + 2 + 32 * RECENT_SENDERS + 1 + 8 + 8 + 32 + 8 + 8 + 1 + 8 + 8 + 32 + 1 + 8 + 8 + 32 + 2 + 1
+        + 8 * RECENT_FEES + 1 + 8 + 2 + 8 + 8 + 8 + 8 + 2 + 8
+        + 8 + 8 + 2 + 1 + 1 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 8 + 1
+        + 2 + 32 + 8 + 8 + 8 + 8
+        + 8 + 8 + 8 + 8
+        + 2 + 8 + 8 + 1 + 8 + 8
+        + 1 + 8 + 8 + 8 + 8;
+
+    /// Deadline after a start/extension at `now`, clamped so the game can never
+    /// run past `first_message_ts + max_total_duration`.
+    pub fn next_deadline(&self, now: i64) -> i64 {
+        self.clamp_deadline(now.checked_add(EXTEND_SECONDS).unwrap())
+    }
+
+    fn clamp_deadline(&self, deadline: i64) -> i64 {
+        if self.max_total_duration == 0 {
+            return deadline;
+        }
+        let ceiling = self
+            .first_message_ts
+            .checked_add(self.max_total_duration)
+            .unwrap();
+        deadline.min(ceiling)
+    }
+
+    /// Deadline after a submission at `now` while the timer runs, or `None` if
+    /// this submission doesn't extend it (always `None` while
+    /// `extensions_frozen` or `extension_gated`). With anti-snipe on
+    /// (`anti_snipe_window > 0`) only submissions in the last
+    /// `anti_snipe_window` seconds extend, to `now + anti_snipe_extension`, and
+    /// the deadline never moves backwards.
+    pub fn extended_deadline(&self, now: i64) -> Option<i64> {
+        if self.extensions_frozen || self.extension_gated() || self.extensions_remaining() == 0 {
+            return None;
+        }
+        if self.anti_snipe_window == 0 {
+            return Some(self.next_deadline(now));
+        }
+        if self.deadline.saturating_sub(now) > self.anti_snipe_window {
+            return None;
+        }
+        let extended = self.clamp_deadline(now.checked_add(self.anti_snipe_extension).unwrap());
+        Some(extended.max(self.deadline))
+    }
+
+    /// Collapses the splitter back to the single `marketing_wallet` recipient.
+    pub fn set_single_marketing_recipient(&mut self, wallet: Pubkey, bps: u16) {
+        self.marketing_splits = [MarketingSplit::default(); MAX_MARKETING_SPLITS];
+        self.marketing_splits[0] = MarketingSplit { wallet, bps };
+    }
+
+    /// Marketing skim on `fee` at `marketing_bps`, before any waiver.
+    pub fn marketing_cut(&self, fee: u64) -> u64 {
+        // marketing_fee = fee * bps / 10000
+        (fee as u128)
+            .checked_mul(self.marketing_bps as u128)
+            .unwrap()
+            .checked_div(10_000)
+            .unwrap() as u64
+    }
+
+    /// Extra, prize-only charge on `fee` for a wallet resubmitting within
+    /// `self_submit_cooldown` of its own last submission this game.
+    pub fn self_submit_penalty(&self, participant: &Participant, fee: u64, now: i64) -> u64 {
+        let recent = participant.wallet != Pubkey::default()
+            && participant.game_number == self.game_number
+            && now.saturating_sub(participant.last_submit_ts) < self.self_submit_cooldown;
+        if !recent {
+            return 0;
+        }
+        self.self_submit_penalty_cut(fee)
+    }
+
+    pub fn self_submit_penalty_cut(&self, fee: u64) -> u64 {
+        (fee as u128)
+            .checked_mul(self.self_submit_penalty_bps as u128)
+            .unwrap()
+            .checked_div(10_000)
+            .unwrap() as u64
+    }
+
+    /// Staking pool's slice of a `marketing_fee`, `staking_bps` out of
+    /// `marketing_bps`.
+    pub fn staking_cut(&self, marketing_fee: u64) -> u64 {
+        if self.staking_bps == 0 || self.marketing_bps == 0 {
+            return 0;
+        }
+        (marketing_fee as u128)
+            .checked_mul(self.staking_bps as u128)
+            .unwrap()
+            .checked_div(self.marketing_bps as u128)
+            .unwrap() as u64
+    }
+
+    /// Once the timer runs, `endgame_waive_marketing` sends the whole fee to the pot.
+    pub fn marketing_waived(&self) -> bool {
+        self.endgame_waive_marketing && self.timer_active
+    }
+
+    /// Splits a fee into `(marketing_fee, prize_fee)`.
+    pub fn split_fee(&self, fee: u64) -> (u64, u64) {
+        let marketing_fee = if self.marketing_waived()
+            || self.marketing_mode == marketing_mode::AT_CLAIM
+        {
+            0
+        } else {
+            // past `marketing_cap` the whole fee goes to the pot
+            self.marketing_share(fee)
+                .min(self.marketing_cap.saturating_sub(self.total_marketing_paid))
+        };
+
+        // prize portion is whatever's left after marketing skim; can't
+        // underflow since `marketing_bps <= MAX_MARKETING_BPS < 10_000`
+        let prize_fee: u64 = fee.checked_sub(marketing_fee).unwrap();
+
+        (marketing_fee, prize_fee)
+    }
+
+    /// Counts `amount` toward `marketing_cap`, announcing the submission that
+    /// reaches it.
+    fn add_marketing_paid(&mut self, amount: u64) {
+        if amount == 0 {
+            return;
+        }
+        self.total_marketing_paid = self.total_marketing_paid.checked_add(amount).unwrap();
+        if self.total_marketing_paid >= self.marketing_cap {
+            emit!(MarketingCapReached {
+                total: self.total_marketing_paid,
+            });
+        }
+    }
+
+    /// `current_fee` after one more submission: `FEE_GROWTH_BPS` of growth,
+    /// at least `min_fee_increment`, capped at `fee_cap`.
+    pub fn next_fee(&self) -> u64 {
+        let next_fee = (self.current_fee as u128)
+            .checked_mul(10_000 + FEE_GROWTH_BPS as u128)
+            .unwrap()
+            .checked_div(10_000)
+            .unwrap()
+            // the next player always pays at least `min_fee_increment` more, even
+            // when the percentage growth rounds down to nothing
+            .max(self.current_fee as u128 + self.min_fee_increment as u128);
+
+        // clamp while still in u128: near u64::MAX the bumped fee no longer fits
+        // and casting first would truncate it to a bogus small value
+        next_fee.min(self.fee_cap as u128) as u64
+    }
+
+    /// Cut off `current_fee` for challenging a leader who has held for at
+    /// least `hold_reward_interval` seconds of a live timer.
+    pub fn hold_discount(&self, now: i64) -> u64 {
+        if self.hold_reward_interval == 0 || !self.timer_active {
+            return 0;
+        }
+        if now.saturating_sub(self.last_submit_ts) < self.hold_reward_interval {
+            return 0;
+        }
+        (self.current_fee as u128)
+            .checked_mul(self.hold_reward_bps as u128)
+            .unwrap()
+            .checked_div(10_000)
+            .unwrap() as u64
+    }
+
+    /// What the next submitter pays at `now`: `current_fee` less any hold
+    /// reward. The fee curve itself keeps stepping from `current_fee`.
+    pub fn fee_due(&self, now: i64) -> u64 {
+        self.current_fee - self.hold_discount(now)
+    }
+
+    /// `max_single_fee` is a hard ceiling on any single charge, separate
+    /// from `fee_cap`; `min_fee_paid` is the floor.
+    pub fn require_fee_in_bounds(&self, fee: u64) -> Result<()> {
+        let reason = self.fee_status(fee, 0, 0);
+        require!(reason == submit_reason::ELIGIBLE, submit_error(reason));
+        Ok(())
+    }
+
+    /// The fee guards as a `submit_reason` code: `fee` within bounds and a
+    /// payer holding `payer_lamports` able to cover `charge` (the fee plus
+    /// any self-submit penalty).
+    pub fn fee_status(&self, fee: u64, charge: u64, payer_lamports: u64) -> u8 {
+        if fee > self.max_single_fee {
+            submit_reason::FEE_TOO_LARGE
+        } else if fee < self.min_fee_paid {
+            submit_reason::FEE_TOO_LOW
+        } else if payer_lamports < charge {
+            submit_reason::INSUFFICIENT_FUNDS
+        } else {
+            submit_reason::ELIGIBLE
+        }
+    }
+
+    /// Whether `max_submissions_per_slot` is already used up in `slot`.
+    pub fn slot_full(&self, slot: u64) -> bool {
+        let used = if slot == self.last_slot { self.submissions_this_slot } else { 0 };
+        used >= self.max_submissions_per_slot
+    }
+
+    fn emit_hold_reward(&self, now: i64) {
+        let discount = self.hold_discount(now);
+        if discount > 0 {
+            emit!(HoldRewardApplied {
+                discount,
+                fee_paid: self.current_fee - discount,
+            });
+        }
+    }
+
+    /// `marketing_cut`, raised to `marketing_min` when that rounds low but
+    /// never past `fee` itself. No floor while `marketing_bps` is 0.
+    pub fn marketing_share(&self, fee: u64) -> u64 {
+        if self.marketing_bps == 0 {
+            return 0;
+        }
+        self.marketing_cut(fee).max(self.marketing_min).min(fee)
+    }
+
+    fn emit_marketing_floor(&self, fee: u64) {
+        let computed = self.marketing_cut(fee);
+        if self.marketing_waived()
+            || self.marketing_mode == marketing_mode::AT_CLAIM
+            || self.marketing_share(fee) == computed
+        {
+            return;
+        }
+        emit!(MarketingFloorApplied {
+            computed,
+            amount: self.split_fee(fee).0,
+        });
+    }
+
+    fn emit_marketing_waived(&self, fee: u64) {
+        if self.marketing_waived() {
+            emit!(MarketingWaived {
+                amount: self.marketing_cut(fee),
+            });
+        }
+    }
+
+    /// Slot in `recent_senders` that receives the bonus, if one is configured.
+    ///
+    /// This is NOT secure randomness. The seed (`deadline`, `messages_count`,
+    /// `last_sender`) is frozen by the last submission, which is what lets the
+    /// claimer compute the index off-chain and pass the matching account, but
+    /// it also means the final submitter can predict the outcome and grind it
+    /// (e.g. by timing their message) to steer the bonus. Keep `bonus_bps`
+    /// small enough that this isn't worth doing.
+    pub fn bonus_index(&self) -> Option<usize> {
+        let filled = self.messages_count.min(RECENT_SENDERS as u64) as usize;
+        if self.bonus_bps == 0 || filled == 0 {
+            return None;
+        }
+        let seed = hashv(&[
+            &self.deadline.to_le_bytes(),
+            &self.messages_count.to_le_bytes(),
+            self.last_sender.as_ref(),
+        ]);
+        let r = u64::from_le_bytes(seed.to_bytes()[..8].try_into().unwrap());
+        Some((r % filled as u64) as usize)
+    }
+
+    /// Bonus carved out of a pot of `balance`, 0 when no bonus applies.
+    pub fn bonus_amount(&self, balance: u64) -> u64 {
+        if self.bonus_index().is_none() {
+            return 0;
+        }
+        (balance as u128)
+            .checked_mul(self.bonus_bps as u128)
+            .unwrap()
+            .checked_div(10_000)
+            .unwrap() as u64
+    }
+
+    /// Earliest time `claim_prize` accepts,
+    /// `deadline - deadline_skew_tolerance + claim_delay_after_deadline`.
+    pub fn claimable_from(&self) -> i64 {
+        self.deadline
+            .checked_sub(self.deadline_skew_tolerance)
+            .unwrap()
+            .checked_add(self.claim_delay_after_deadline)
+            .unwrap()
+    }
+
+    /// Latest time a submission is accepted (and extends the timer),
+    /// `deadline + deadline_skew_tolerance`.
+    ///
+    /// Both bounds widen by the same tolerance, so for `2 * tolerance` seconds
+    /// around `deadline` a claim and a late submission are both valid and
+    /// whichever lands first wins: a claim ends the game, a submission pushes
+    /// the deadline out. A non-zero tolerance trades a sharp cutoff for
+    /// consistent answers across RPCs whose clocks disagree.
+    pub fn submission_deadline(&self) -> i64 {
+        self.deadline.checked_add(self.deadline_skew_tolerance).unwrap()
+    }
+
+    /// Whether a submission at `now` still beats the deadline. With
+    /// `strict_deadline` the deadline second itself is already too late.
+    pub fn before_submission_deadline(&self, now: i64) -> bool {
+        if self.strict_deadline {
+            now < self.submission_deadline()
+        } else {
+            now <= self.submission_deadline()
+        }
+    }
+
+    /// Called as `last_sender` is outbid in `slot`. If they paid in the same or
+    /// the previous slot, they may reclaim `sniped_refund_bps` of the prize
+    /// leg that submission put in the vault. Only the latest such submission
+    /// is kept.
+    pub fn note_snipe(&mut self, slot: u64, prev_slot: u64) {
+        if self.sniped_refund_bps == 0
+            || self.last_sender == Pubkey::default()
+            || slot > prev_slot.saturating_add(1)
+        {
+            return;
+        }
+        self.sniped_wallet = self.last_sender;
+        self.sniped_refund = (self.last_prize_fee as u128)
+            .checked_mul(self.sniped_refund_bps as u128)
+            .unwrap()
+            .checked_div(10_000)
+            .unwrap() as u64;
+    }
+
+    /// Too few distinct players so far for submissions to extend the timer.
+    /// The submitter's own first message counts (see `join_game`).
+    pub fn extension_gated(&self) -> bool {
+        self.unique_participants < self.min_unique_for_extension
+    }
+
+    /// Timer extensions left before the deadline locks in; `u64::MAX` when
+    /// `max_extensions` is 0 (unlimited), 0 once `schedule_end` has pinned it.
+    pub fn extensions_remaining(&self) -> u64 {
+        if self.scheduled_end {
+            return 0;
+        }
+        if self.max_extensions == 0 {
+            return u64::MAX;
+        }
+        self.max_extensions.saturating_sub(self.extension_count)
+    }
+
+    /// `winner_rebate_bps` of what the winner paid in fees this game, before
+    /// capping to the pot.
+    pub fn winner_rebate(&self, participant: Option<&Participant>) -> u64 {
+        let Some(participant) = participant else {
+            return 0;
+        };
+        if participant.game_number != self.game_number {
+            return 0;
+        }
+        self.winner_rebate_cut(participant.contributed)
+    }
+
+    pub fn winner_rebate_cut(&self, contributed: u64) -> u64 {
+        (contributed as u128)
+            .checked_mul(self.winner_rebate_bps as u128)
+            .unwrap()
+            .checked_div(10_000)
+            .unwrap() as u64
+    }
+
+    /// The pot `pay_out` works from with `vault` lamports in the vault, and
+    /// the rent-exempt remainder it leaves behind. The leader's own deposits
+    /// are released into it, a remainder too small to stay rent-exempt goes
+    /// with it, and it's capped at `max_pot`. `decay` comes off both the
+    /// vault and the pot first, as an `apply_decay` landing ahead would.
+    pub fn payout_pot(&self, vault: u64, decay: u64, rent_lamports: u64) -> (u64, u64) {
+        let pot = self
+            .payable_balance(vault)
+            .saturating_add(self.leader_deposit)
+            .min(vault)
+            - decay;
+        let vault = vault - decay;
+        let pot = if vault - pot < rent_lamports { vault } else { pot };
+        (pot.min(self.max_pot), vault - pot)
+    }
+
+    /// How `pay_out` divides a pot of `balance`: `rollover_bps` held back,
+    /// then the `rebate` and the gas top-up (for a winner holding
+    /// `winner_lamports`) set aside, then claim-time marketing, the platform
+    /// fee and the bonus off what's left. The single source for both the
+    /// payout and every preview of it.
+    pub fn payout_split(
+        &self,
+        config: &Config,
+        balance: u64,
+        rollover_bps: u16,
+        rebate: u64,
+        winner_lamports: u64,
+        rent_lamports: u64,
+    ) -> PayoutSplit {
+        // a rollover too small to keep the vault rent-exempt goes to the
+        // winner instead; the restart tops the vault up anyway
+        let rollover = (balance as u128)
+            .checked_mul(rollover_bps as u128)
+            .unwrap()
+            .checked_div(10_000)
+            .unwrap() as u64;
+        let rollover = if rollover < rent_lamports { 0 } else { rollover };
+        let rest = balance - rollover;
+
+        let rebate = rebate.min(rest);
+        let rest = rest - rebate;
+        // a winner short of `gas_reserve` gets topped up to it ahead of every
+        // other cut, so even a dust pot leaves them able to transact
+        let gas = self.gas_reserve.saturating_sub(winner_lamports).min(rest);
+        let rest = rest - gas;
+
+        let at_claim = self.marketing_mode == marketing_mode::AT_CLAIM;
+        let marketing = if at_claim && self.marketing_bps > 0 {
+            self.marketing_cut(rest)
+                .min(self.marketing_cap.saturating_sub(self.total_marketing_paid))
+        } else {
+            0
+        };
+        let rest = rest - marketing;
+        let platform_fee = config.platform_cut(rest);
+        let rest = rest - platform_fee;
+        let bonus = self.bonus_amount(rest);
+
+        PayoutSplit {
+            rollover,
+            rebate,
+            gas,
+            marketing,
+            platform_fee,
+            bonus,
+            prize: rest - bonus + rebate + gas,
+        }
+    }
+
+    /// What a winner walks away with from a pot of `balance`, per
+    /// `payout_split`. Leaves out sponsor top-ups, rollover and the gas
+    /// top-up.
+    pub fn winner_take(&self, config: &Config, balance: u64, rebate: u64) -> u64 {
+        self.payout_split(config, balance, 0, rebate, u64::MAX, 0).prize
+    }
+
+    // at `max_participants` only wallets that already have a PDA get in.
+    // `participant` is the payer's PDA; `None`, or one `init_if_needed` just
+    // created (still zeroed), is a new wallet.
+    pub fn participant_limit_reached(&self, participant: Option<&Participant>) -> bool {
+        let new_wallet = participant.is_none_or(|p| p.wallet == Pubkey::default());
+        new_wallet && self.unique_participants >= self.max_participants
+    }
+
+    /// The last `settlement_buffer` seconds before `deadline`, when only a
+    /// claim can land: no submission can extend the deadline out from
+    /// under it.
+    pub fn in_settlement_window(&self, now: i64) -> bool {
+        self.timer_active
+            && self.settlement_buffer > 0
+            && now >= self.deadline.saturating_sub(self.settlement_buffer)
+    }
+
+    /// How much of `prize_fee` would push the pot past `max_pot`.
+    pub fn pot_excess(&self, prize_fee: u64, vault_lamports: u64) -> u64 {
+        let room = self
+            .max_pot
+            .saturating_sub(self.payable_balance(vault_lamports));
+        prize_fee.saturating_sub(room)
+    }
+
+    /// What `apply_decay` may burn from `pot` at `now`, and the whole hours
+    /// the claim window has been open. Measured against `decay_base` once
+    /// decay has started, else against `pot` itself.
+    pub fn decay_due(&self, pot: u64, now: i64) -> (u64, u64) {
+        let hours = now.saturating_sub(self.claimable_from()).max(0) as u64 / 3600;
+        let base = if self.decay_base == 0 { pot } else { self.decay_base };
+        let decay_bps = (self.post_deadline_decay_bps_per_hour as u64)
+            .saturating_mul(hours)
+            .min(MAX_POST_DEADLINE_DECAY_BPS as u64);
+        let target = (base as u128)
+            .checked_mul(decay_bps as u128)
+            .unwrap()
+            .checked_div(10_000)
+            .unwrap() as u64;
+        (target.saturating_sub(self.decay_burned).min(pot), hours)
+    }
+
+    /// Under `prevent_self_deal`, rejects a `winner` who is the authority or
+    /// takes any share of the marketing rake.
+    pub fn require_no_self_deal(&self, winner: Pubkey) -> Result<()> {
+        require!(!self.is_self_deal(winner), ErrorCode::SelfDealBlocked);
+        Ok(())
+    }
+
+    fn is_self_deal(&self, winner: Pubkey) -> bool {
+        if !self.prevent_self_deal {
+            return false;
+        }
+        let marketing = winner == self.marketing_wallet
+            || self
+                .marketing_splits
+                .iter()
+                .any(|split| split.bps > 0 && split.wallet == winner);
+        marketing || winner == self.authority
+    }
+
+    /// Rate-limits the fee/marketing setters to one per
+    /// `param_change_cooldown`, stamping the change when it's allowed.
+    pub fn note_param_change(&mut self, now: i64) -> Result<()> {
+        require!(
+            now >= self.last_param_change_ts.saturating_add(self.param_change_cooldown),
+            ErrorCode::ParamChangeTooSoon
+        );
+        self.last_param_change_ts = now;
+        Ok(())
+    }
+
+    /// Past the `params_locked_after` correction window, players already paid
+    /// at the current rate, so the rake may only be lowered. Once the endgame
+    /// timer runs it can't move at all until the game ends; re-sending the
+    /// current bps (e.g. to rotate the wallet) is always fine.
+    pub fn require_marketing_bps_change(&self, new_bps: u16) -> Result<()> {
+        if new_bps == self.marketing_bps {
+            return Ok(());
+        }
+        require!(
+            !self.timer_active || self.ended,
+            ErrorCode::ParamsLockedDuringEndgame
+        );
+        if self.params_locked_after > 0 && self.messages_count > self.params_locked_after {
+            require!(new_bps <= self.marketing_bps, ErrorCode::ParamsLocked);
+        }
+        Ok(())
+    }
+
+    /// While the endgame timer runs `base_fee` can be lowered or held, never
+    /// raised: raising the floor would reprice the very next play.
+    pub fn require_base_fee_change(&self, new_base_fee: u64) -> Result<()> {
+        require!(
+            !self.timer_active || self.ended || new_base_fee <= self.base_fee,
+            ErrorCode::BaseFeeLockedDuringGame
+        );
+        Ok(())
+    }
+
+    /// Same rule for a direct `set_current_fee` override: the next player's
+    /// price can drop or hold during the endgame, not jump.
+    pub fn require_current_fee_change(&self, new_fee: u64) -> Result<()> {
+        require!(
+            !self.timer_active || self.ended || new_fee <= self.current_fee,
+            ErrorCode::CurrentFeeLockedDuringGame
+        );
+        Ok(())
+    }
+
+    /// The pot a payout works from: the whole vault, or only the accounted
+    /// prize lamports under `strict_accounting`.
+    pub fn payable_balance(&self, vault_lamports: u64) -> u64 {
+        if self.strict_accounting {
+            self.accounted_balance.min(vault_lamports)
+        } else {
+            vault_lamports.saturating_sub(self.deposits_outstanding)
+        }
+    }
+
+    pub fn restart_allowed_at(&self) -> i64 {
+        self.ended_ts.checked_add(self.restart_cooldown).unwrap()
+    }
+
+    /// Runs the `claim_prize` guards for `caller` and returns the first one that
+    /// fails as a `claim_reason` code.
+    pub fn claim_status(&self, config: &Config, caller: Pubkey, now: i64) -> u8 {
+        if config.global_paused {
+            claim_reason::GLOBALLY_PAUSED
+        } else if !self.timer_active {
+            claim_reason::TIMER_NOT_ACTIVE
+        } else if now < self.claimable_from() && !self.deadline_reached {
+            claim_reason::DEADLINE_NOT_REACHED
+        } else if self.last_sender == Pubkey::default() {
+            claim_reason::NO_WINNER
+        } else if self.ended {
+            claim_reason::ALREADY_CLAIMED
+        } else if caller != self.last_sender {
+            claim_reason::NOT_THE_WINNER
+        } else if self.unique_participants < self.min_unique_to_claim {
+            claim_reason::NOT_ENOUGH_PLAYERS
+        } else if self.is_self_deal(caller) {
+            claim_reason::SELF_DEAL_BLOCKED
+        } else {
+            claim_reason::ELIGIBLE
+        }
+    }
+}
+
+/// `pay_out`'s cuts of a pot, from `Escrow::payout_split`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PayoutSplit {
+    /// held in the vault to seed the next game
+    pub rollover: u64,
+    pub rebate: u64,
+    pub gas: u64,
+    pub marketing: u64,
+    pub platform_fee: u64,
+    pub bonus: u64,
+    /// the winner's share: what's left after the cuts, plus `rebate` and `gas`
+    pub prize: u64,
+}
+
+/// Per-wallet state at `[b"participant", wallet]`, shared across games.
+/// `messages_count` and `contributed` only cover `game_number`.
+#[account]
+pub struct Participant {
+    pub wallet: Pubkey,
+    pub bump: u8,
+    pub game_number: u64,
+    pub messages_count: u64,
+    /// lamports paid in fees this game
+    pub contributed: u64,
+    /// first submission ever, in any game
+    pub first_ts: i64,
+    /// `client_nonce` of the last accepted `submit_message`
+    pub last_client_nonce: Option<u64>,
+    pub last_submit_ts: i64,
+    /// `deposit_mode` deposits made in game `deposit_game` and not yet
+    /// reclaimed
+    pub deposited: u64,
+    pub deposit_game: u64,
+}
+
+impl Participant {
+    pub const LEN: usize = 32 + 1 + 8 + 8 + 8 + 8 + 1 + 8 + 8 + 8 + 8;
+}
+
+/// Platform-wide singleton (`[b"config"]`) owned by the jigsaw admin rather
+/// than any single game's authority.
+#[account]
+pub struct Config {
+    pub admin: Pubkey,
+    pub global_paused: bool,
+    pub bump: u8,
+    /// receives `platform_claim_bps` of every payout
+    pub platform_wallet: Pubkey,
+    pub platform_claim_bps: u16,
+    /// `initialize` rejects a marketing recipient equal to the authority
+    pub require_distinct_marketing: bool,
+}
+
+impl Config {
+    pub const LEN: usize = 32 + 1 + 1 + 32 + 2 + 1;
+
+    /// Platform's share of a payout of `balance`.
+    pub fn platform_cut(&self, balance: u64) -> u64 {
+        (balance as u128)
+            .checked_mul(self.platform_claim_bps as u128)
+            .unwrap()
+            .checked_div(10_000)
+            .unwrap() as u64
+    }
+
+    /// Platform policy under `require_distinct_marketing`: the operator can't
+    /// also be the rake recipient.
+    pub fn require_distinct_marketing_wallet(
+        &self,
+        authority: &Pubkey,
+        wallet: &Pubkey,
+    ) -> Result<()> {
+        require!(
+            !self.require_distinct_marketing || wallet != authority,
+            ErrorCode::InvalidMarketingWallet
+        );
+        Ok(())
+    }
+}
+
+#[event]
+pub struct MessageSubmitted {
+    pub sender: Pubkey,
+    pub msg_hash: [u8; 32],
+    pub fee_paid: u64,
+    pub new_fee: u64,
+    pub fee_step: u64,
+    pub timestamp: i64,
+    pub submissions_this_slot: u64,
+    /// whether any of `fee_paid` went to marketing, and how much
+    pub marketing_applied: bool,
+    pub marketing_amount: u64,
+}
+
+#[event]
+pub struct TimerStarted {
+    pub deadline: i64,
+}
+
+#[event]
+pub struct TimerExtended {
+    pub new_deadline: i64,
+    /// extended by the anti-snipe rule rather than the normal per-message one
+    pub anti_snipe: bool,
+    /// `u64::MAX` when extensions are unlimited
+    pub extensions_remaining: u64,
+}
+
+#[event]
+pub struct MarketingFeeSent {
+    pub wallet: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct MarketingParamsUpdated {
+    pub wallet: Pubkey,
+    pub bps: u16,
+}
+
+#[event]
+pub struct PrizeClaimed {
+    pub winner: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ClaimEligibility {
+    pub eligible: bool,
+    pub reason_code: u8,
+    pub amount: u64,
+}
+
+#[event]
+pub struct GlobalPauseToggled {
+    pub admin: Pubkey,
+    pub paused: bool,
+}
+
+#[event]
+pub struct CurrentFeeOverridden {
+    pub old: u64,
+    pub new: u64,
+}
+
+#[event]
+pub struct CurrentFeeClamped {
+    pub old_fee: u64,
+    pub new_fee: u64,
+    pub reason: u8,
+}
+
+#[event]
+pub struct GuaranteeShortfall {
+    pub guaranteed: u64,
+    pub paid: u64,
+    pub uncovered: u64,
+}
+
+#[event]
+pub struct GameEnded {
+    pub winner: Pubkey,
+    pub ended_ts: i64,
+    pub claimable_from: i64,
+    pub restart_allowed_at: i64,
+    pub max_fee_paid: u64,
+}
+
+#[event]
+pub struct GameRestarted {
+    pub base_fee: u64,
+    pub fee_cap: u64,
+    pub vault_balance: u64,
+}
+
+#[event]
+pub struct MarketingWaived {
+    pub amount: u64,
+}
+
+#[event]
+pub struct PayoutPreview {
+    pub gross: u64,
+    pub retained_rent: u64,
+    pub net: u64,
+}
+
+#[event]
+pub struct PrizeForfeited {
+    pub winner: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct BonusAwarded {
+    pub wallet: Pubkey,
+    pub amount: u64,
+    pub index: u8,
+}
+
+/// `head` is the slot the next sender will overwrite, so reading from `head`
+/// and wrapping gives oldest-to-newest.
+#[event]
+pub struct RecentSenders {
+    pub senders: [Pubkey; RECENT_SENDERS],
+    pub head: u8,
+}
+
+#[event]
+pub struct UsdFeeConverted {
+    pub fee_usd_cents: u64,
+    pub price: i64,
+    pub exponent: i32,
+    pub lamports: u64,
+}
+
+#[event]
+pub struct MarketingSelfTransferSkipped {
+    pub wallet: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct EscrowResized {
+    pub new_len: u32,
+}
+
+#[event]
+pub struct PlatformFeeCollected {
+    pub wallet: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct DuplicateSubmitIgnored {
+    pub payer: Pubkey,
+    pub client_nonce: u64,
+}
+
+#[event]
+pub struct MessageVerified {
+    pub matches: bool,
+}
+
+#[event]
+pub struct UnaccountedSwept {
+    pub amount: u64,
+}
+
+#[event]
+pub struct StakingFeeSent {
+    pub amount: u64,
+}
+
+#[event]
+pub struct ResolvedPdas {
+    pub escrow: Pubkey,
+    pub escrow_vault: Pubkey,
+    pub escrow_bump: u8,
+    pub vault_bump: u8,
+}
+
+/// Same ring layout as `RecentSenders`: start at `head` for oldest-to-newest.
+#[event]
+pub struct RecentFees {
+    pub fees: [u64; RECENT_FEES],
+    pub head: u8,
+}
+
+#[event]
+pub struct SelfSubmitPenalty {
+    pub amount: u64,
+}
+
+#[event]
+pub struct ExtensionsRemaining {
+    /// `u64::MAX` when extensions are unlimited
+    pub remaining: u64,
+}
+
+#[event]
+pub struct WinnerRebate {
+    pub amount: u64,
+}
+
+#[event]
+pub struct MyFee {
+    pub base_current_fee: u64,
+    pub effective_fee: u64,
+    pub applied_discount_bps: u16,
+    pub applied_penalty_bps: u16,
+}
+
+#[event]
+pub struct SubmitEligibility {
+    pub eligible: bool,
+    pub reason_code: u8,
+    pub required_fee: u64,
+}
+
+// `BreakEven` is taken by the event, hence `BreakEvenCheck` for the accounts
+#[event]
+pub struct BreakEven {
+    pub current_fee: u64,
+    pub pot: u64,
+    pub is_profitable: bool,
+}
+
+#[event]
+pub struct FeePolicy {
+    pub base_fee: u64,
+    pub fee_cap: u64,
+    pub current_fee: u64,
+    pub fee_growth_bps: u16,
+    pub fee_mode: u8,
+    /// what the next submission will add to `current_fee`
+    pub fee_step: u64,
+    pub min_fee_increment: u64,
+    pub fee_floor: u64,
+    pub marketing_bps: u16,
+}
+
+// `Checkpoint` is taken by the accounts struct
+#[event]
+pub struct CheckpointTaken {
+    pub messages_count: u64,
+    pub current_fee: u64,
+    pub deadline: i64,
+    pub timer_active: bool,
+    pub ended: bool,
+    pub last_sender: Pubkey,
+    pub pot: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct FullState {
+    pub state: Box<Escrow>,
+    pub vault_balance: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct PrizeDecayed {
+    pub burned: u64,
+    pub hours: u64,
+}
+
+#[event]
+pub struct WinnerPayout {
+    pub gross: u64,
+    pub platform_fee: u64,
+    pub marketing: u64,
+    pub bonus: u64,
+    pub decay: u64,
+    pub rent_retained: u64,
+    pub net: u64,
+}
 
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct ConsolationList {
+    pub wallets: Vec<Pubkey>,
 }
 
-#[derive(Accounts)]
-pub struct ClaimPrize<'info> {
-    #[account(mut)]
-    pub winner: Signer<'info>,
+#[event]
+pub struct PotCapped {
+    pub excess: u64,
+}
 
-    #[account(
-        mut,
-        seeds = [b"escrow"],
-        bump = escrow.bump
-    )]
-    pub escrow: Account<'info, Escrow>,
+#[event]
+pub struct EconomicSummary {
+    pub total_fees_collected: u64,
+    pub total_marketing_paid: u64,
+    pub total_prize_paid: u64,
+    pub current_vault_balance: u64,
+    /// i128 so three full u64 totals can't overflow it
+    pub net: i128,
+}
 
-    /// CHECK:
-    /// Program-owned vault PDA that holds the pooled lamports.
-    /// We'll sign for it with [b"escrow", b"vault", bump] and transfer out all lamports.
-    #[account(
-        mut,
-        seeds = [b"escrow", b"vault"],
-        bump
-    )]
-    pub escrow_vault: SystemAccount<'info>,
+#[event]
+pub struct DepositReclaimed {
+    pub wallet: Pubkey,
+    pub amount: u64,
+}
 
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct SecurityInfo {
+    pub program_id: Pubkey,
+    pub name: String,
+    pub project_url: String,
+    pub contacts: String,
+    pub policy: String,
+    pub source_code: String,
 }
 
+#[event]
+pub struct HoldRewardApplied {
+    pub discount: u64,
+    pub fee_paid: u64,
+}
 
-#[derive(Accounts)]
-pub struct JigsawApprovePayout<'info> {
-    /// CHECK:
-    /// authority == jigsaw_approver in the `escrow` constraint, so this is safe.
-    pub jigsaw_approver: Signer<'info>,
+#[event]
+pub struct AuthorityCheck {
+    pub is_authority: bool,
+    pub is_jigsaw_approver: bool,
+}
 
-    #[account(
-        mut,
-        seeds = [b"escrow"],
-        bump = escrow.bump,
-        constraint = escrow.authority == jigsaw_approver.key() @ ErrorCode::Unauthorized
-    )]
-    pub escrow: Account<'info, Escrow>,
+#[event]
+pub struct DeadlineLatched {
+    pub deadline: i64,
+}
 
-    /// CHECK:
-    /// `winner` is just the payout destination. We never read or mutate its data,
-    /// we only send lamports to it via a system transfer. So it does not need to
-    /// satisfy any owner/type invariants.
-    #[account(mut)]
-    pub winner: UncheckedAccount<'info>,
+#[event]
+pub struct EndScheduled {
+    pub end_time: i64,
+}
 
-    /// CHECK:
-    /// `escrow_vault` is the program-owned PDA `[b"escrow", b"vault"]` that holds
-    /// the prize pool lamports. We sign for it with `invoke_signed` using those seeds.
-    #[account(
-        mut,
-        seeds = [b"escrow", b"vault"],
-        bump
-    )]
-    pub escrow_vault: SystemAccount<'info>,
+#[event]
+pub struct MarketingCapReached {
+    pub total: u64,
+}
 
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct MarketingFloorApplied {
+    /// the bps cut before the floor
+    pub computed: u64,
+    /// marketing actually taken (after `marketing_cap`)
+    pub amount: u64,
 }
 
-#[derive(Accounts)]
-pub struct SetFeeParams<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    #[account(
-        mut,
-        seeds = [b"escrow"],
-        bump = escrow.bump,
-        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
-    )]
-    pub escrow: Account<'info, Escrow>,
+#[event]
+pub struct ExtensionsFrozenToggled {
+    pub frozen: bool,
 }
 
-#[derive(Accounts)]
-pub struct SetMarketingParams<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    #[account(
-        mut,
-        seeds = [b"escrow"],
-        bump = escrow.bump,
-        constraint = escrow.authority == authority.key() @ ErrorCode::Unauthorized
-    )]
-    pub escrow: Account<'info, Escrow>,
-    
-    /// CHECK: New marketing wallet
-    pub marketing_wallet: UncheckedAccount<'info>,
+#[event]
+pub struct MarketingRecipients {
+    pub recipients: Vec<MarketingSplit>,
 }
 
-#[account]
-pub struct Escrow {
-    pub authority: Pubkey,
-    pub base_fee: u64,
-    pub fee_cap: u64,
-    pub current_fee: u64,
-    pub marketing_wallet: Pubkey,
-    pub marketing_bps: u16,
-    pub messages_count: u64,
-    pub last_sender: Pubkey,
-    pub timer_active: bool,
-    pub deadline: i64,
-    pub ended: bool,
-    pub bump: u8,
+#[event]
+pub struct ParticipationProof {
+    pub wallet: Pubkey,
+    pub count: u64,
+    pub contributed: u64,
+    pub first_ts: i64,
 }
 
-impl Escrow {
-    pub const LEN: usize = 32 + 8 + 8 + 8 + 32 + 2 + 8 + 32 + 1 + 8 + 1 + 1;
+#[event]
+pub struct ProjectedEnd {
+    pub end_time: i64,
+    pub hit_cap: bool,
 }
 
 #[event]
-pub struct MessageSubmitted {
-    pub sender: Pubkey,
-    pub msg_hash: [u8; 32],
-    pub fee_paid: u64,
-    pub new_fee: u64,
-    pub timestamp: i64,
+pub struct LeaderInfo {
+    pub leader: Pubkey,
+    pub leader_since: i64,
+    pub held_seconds: i64,
 }
 
 #[event]
-pub struct TimerStarted {
-    pub deadline: i64,
+pub struct MarketingModeSet {
+    pub mode: u8,
 }
 
 #[event]
-pub struct TimerExtended {
-    pub new_deadline: i64,
+pub struct ClaimMarketingCollected {
+    pub wallet: Pubkey,
+    pub amount: u64,
 }
 
 #[event]
-pub struct MarketingFeeSent {
+pub struct CombinedPayout {
     pub wallet: Pubkey,
     pub amount: u64,
 }
 
 #[event]
-pub struct MarketingParamsUpdated {
+pub struct SnipedRefund {
     pub wallet: Pubkey,
-    pub bps: u16,
+    pub amount: u64,
 }
 
 #[event]
-pub struct PrizeClaimed {
-    pub winner: Pubkey,
+pub struct ExtensionWithheld {
+    pub unique_participants: u64,
+    pub required: u64,
+}
+
+#[event]
+pub struct GasReserveApplied {
     pub amount: u64,
 }
 
+#[event]
+pub struct AdminAction {
+    pub action_code: u8,
+    pub actor: Pubkey,
+    pub slot: u64,
+}
+
 #[error_code]
 pub enum ErrorCode {
     #[msg("Game ended")]
@@ -602,5 +6361,477 @@ pub enum ErrorCode {
     BpsTooHigh,
     #[msg("Unauthorized")]
     Unauthorized,
+    #[msg("Protocol paused")]
+    GloballyPaused,
+    #[msg("Vault was not pre-credited with the expected fee")]
+    PrecreditMissing,
+    #[msg("Restart cooldown has not elapsed")]
+    RestartTooSoon,
+    #[msg("Marketing recipient accounts don't match the configured splits")]
+    MarketingRecipientMismatch,
+    #[msg("Bonus winner account doesn't match the selected recent sender")]
+    BonusWinnerMismatch,
+    #[msg("Price account is not the configured oracle")]
+    OracleMismatch,
+    #[msg("Oracle price is stale")]
+    OraclePriceStale,
+    #[msg("Oracle price is too uncertain")]
+    OraclePriceUncertain,
+    #[msg("Platform wallet account doesn't match the config")]
+    PlatformWalletMismatch,
+    #[msg("Marketing bps can only be lowered once params are locked")]
+    ParamsLocked,
+    #[msg("Payer doesn't hold an NFT from the required collection")]
+    NftGateFailed,
+    #[msg("Marketing wallet must be on-curve unless marketing_is_pda is set, and not the authority under require_distinct_marketing")]
+    InvalidMarketingWallet,
+    #[msg("Fee exceeds max_single_fee")]
+    FeeTooLarge,
+    #[msg("Not enough distinct players to claim")]
+    NotEnoughPlayers,
+    #[msg("Too many submissions in this slot")]
+    SlotSubmissionLimit,
+    #[msg("Escrow vault was never funded")]
+    VaultNotInitialized,
+    #[msg("Escrow vault already exists")]
+    AlreadyInitialized,
+    #[msg("Marketing bps can't change while the endgame timer runs")]
+    ParamsLockedDuringEndgame,
+    #[msg("Fee is below the minimum charge")]
+    FeeTooLow,
+    #[msg("Base fee can only be lowered while the endgame timer runs")]
+    BaseFeeLockedDuringGame,
+    #[msg("Parameters changed too recently")]
+    ParamChangeTooSoon,
+    #[msg("Winner is the authority or a marketing recipient")]
+    SelfDealBlocked,
+    #[msg("Submissions are closed for settlement")]
+    SettlementWindow,
+    #[msg("Use submit_deposit in deposit mode, and only there")]
+    DepositModeMismatch,
+    #[msg("No deposit to reclaim for this game")]
+    NoDepositToReclaim,
+    #[msg("Losers' deposits are still in their reclaim window")]
+    DepositsOutstanding,
+    #[msg("Game is full; only existing participants can submit")]
+    ParticipantLimitReached,
+    #[msg("Client nonce matches the payer's last submission")]
+    DuplicateSubmit,
+    #[msg("Current fee can only be lowered while the endgame timer runs")]
+    CurrentFeeLockedDuringGame,
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // all-zero state, as a freshly allocated account deserializes
+    fn escrow() -> Escrow {
+        Escrow::deserialize(&mut &[0u8; Escrow::LEN][..]).unwrap()
+    }
+
+    fn config() -> Config {
+        Config::deserialize(&mut &[0u8; Config::LEN][..]).unwrap()
+    }
+
+    #[test]
+    fn next_fee_clamps_near_u64_max() {
+        let mut escrow = escrow();
+        escrow.current_fee = u64::MAX - 1;
+        escrow.fee_cap = u64::MAX;
+        assert_eq!(escrow.next_fee(), u64::MAX);
+
+        // the bumped fee overflows u64 long before the cap; a cast-first
+        // clamp would truncate it to a small value
+        escrow.current_fee = u64::MAX - 1_000;
+        escrow.fee_cap = u64::MAX - 5;
+        assert_eq!(escrow.next_fee(), u64::MAX - 5);
+
+        escrow.current_fee = u64::MAX / 2;
+        escrow.fee_cap = u64::MAX / 2 + 1;
+        assert_eq!(escrow.next_fee(), u64::MAX / 2 + 1);
+    }
+
+    #[test]
+    fn submission_at_exact_deadline() {
+        let mut escrow = escrow();
+        escrow.deadline = 1_000;
+        assert!(escrow.before_submission_deadline(999));
+        assert!(escrow.before_submission_deadline(1_000));
+        assert!(!escrow.before_submission_deadline(1_001));
+
+        escrow.strict_deadline = true;
+        assert!(escrow.before_submission_deadline(999));
+        assert!(!escrow.before_submission_deadline(1_000));
+    }
+
+    #[test]
+    fn marketing_bps_only_lowers_past_params_locked_after() {
+        let mut escrow = escrow();
+        escrow.marketing_bps = 500;
+        escrow.params_locked_after = 3;
+
+        escrow.messages_count = 3;
+        assert_eq!(escrow.require_marketing_bps_change(800), Ok(()));
+
+        escrow.messages_count = 4;
+        assert_eq!(
+            escrow.require_marketing_bps_change(800),
+            Err(ErrorCode::ParamsLocked.into())
+        );
+        assert_eq!(escrow.require_marketing_bps_change(300), Ok(()));
+        assert_eq!(escrow.require_marketing_bps_change(500), Ok(()));
+    }
+
+    #[test]
+    fn fee_over_tiny_max_single_fee_is_refused() {
+        let mut escrow = escrow();
+        escrow.max_single_fee = 10;
+        assert_eq!(escrow.require_fee_in_bounds(10), Ok(()));
+        assert_eq!(
+            escrow.require_fee_in_bounds(11),
+            Err(ErrorCode::FeeTooLarge.into())
+        );
+
+        escrow.min_fee_paid = 5;
+        assert_eq!(escrow.require_fee_in_bounds(4), Err(ErrorCode::FeeTooLow.into()));
+    }
+
+    // a game whose timer ran out at 1_000 with `winner` leading
+    fn expired(winner: Pubkey) -> Escrow {
+        let mut escrow = escrow();
+        escrow.timer_active = true;
+        escrow.deadline = 1_000;
+        escrow.last_sender = winner;
+        escrow.unique_participants = 1;
+        escrow.min_unique_to_claim = 1;
+        escrow
+    }
+
+    #[test]
+    fn single_wallet_cannot_claim_with_min_unique_two() {
+        let winner = Pubkey::new_unique();
+        let mut escrow = expired(winner);
+        assert_eq!(escrow.claim_status(&config(), winner, 1_000), claim_reason::ELIGIBLE);
+
+        escrow.min_unique_to_claim = 2;
+        assert_eq!(
+            escrow.claim_status(&config(), winner, 1_000),
+            claim_reason::NOT_ENOUGH_PLAYERS
+        );
+
+        escrow.unique_participants = 2;
+        assert_eq!(escrow.claim_status(&config(), winner, 1_000), claim_reason::ELIGIBLE);
+
+        // the global pause outranks every per-game guard
+        let mut config = config();
+        config.global_paused = true;
+        assert_eq!(
+            escrow.claim_status(&config, winner, 1_000),
+            claim_reason::GLOBALLY_PAUSED
+        );
+    }
+
+    #[test]
+    fn marketing_bps_boundaries() {
+        let split = |bps| MarketingSplit {
+            wallet: Pubkey::new_unique(),
+            bps,
+        };
+        assert_eq!(validate_marketing_splits(&[split(2_500)]), Ok(2_500));
+        assert_eq!(
+            validate_marketing_splits(&[split(2_501)]),
+            Err(ErrorCode::BpsTooHigh.into())
+        );
+        assert_eq!(
+            validate_marketing_splits(&[split(10_000)]),
+            Err(ErrorCode::BpsTooHigh.into())
+        );
+        assert_eq!(
+            validate_marketing_splits(&[split(2_000), split(501)]),
+            Err(ErrorCode::BpsTooHigh.into())
+        );
+
+        // at the cap the prize leg still can't underflow
+        let mut escrow = escrow();
+        escrow.marketing_bps = MAX_MARKETING_BPS;
+        escrow.marketing_cap = u64::MAX;
+        let (marketing_fee, prize_fee) = escrow.split_fee(u64::MAX);
+        assert_eq!(marketing_fee, u64::MAX / 4);
+        assert_eq!(marketing_fee + prize_fee, u64::MAX);
+    }
+
+    #[test]
+    fn claim_during_delay_is_refused() {
+        let winner = Pubkey::new_unique();
+        let mut escrow = expired(winner);
+        escrow.claim_delay_after_deadline = 60;
+        assert_eq!(escrow.claimable_from(), 1_060);
+
+        assert_eq!(
+            escrow.claim_status(&config(), winner, 1_030),
+            claim_reason::DEADLINE_NOT_REACHED
+        );
+        assert_eq!(escrow.claim_status(&config(), winner, 1_060), claim_reason::ELIGIBLE);
+    }
+
+    #[test]
+    fn latched_deadline_keeps_claim_open() {
+        let winner = Pubkey::new_unique();
+        let mut escrow = expired(winner);
+        assert_eq!(
+            escrow.claim_status(&config(), winner, 999),
+            claim_reason::DEADLINE_NOT_REACHED
+        );
+
+        // an earlier `latch_deadline` saw the deadline pass; a clock that now
+        // reads earlier can't take the claim back
+        escrow.deadline_reached = true;
+        assert_eq!(escrow.claim_status(&config(), winner, 999), claim_reason::ELIGIBLE);
+    }
+
+    #[test]
+    fn marketing_floor_at_fee_of_one() {
+        let mut escrow = escrow();
+        escrow.marketing_bps = 1_000;
+        escrow.marketing_cap = u64::MAX;
+        // 1 * 1000 / 10000 rounds to nothing
+        assert_eq!(escrow.split_fee(1), (0, 1));
+
+        escrow.marketing_min = 1;
+        assert_eq!(escrow.split_fee(1), (1, 0));
+
+        // the floor never takes more than the fee itself
+        escrow.marketing_min = 5;
+        assert_eq!(escrow.split_fee(1), (1, 0));
+
+        // and doesn't apply at all with marketing off
+        escrow.marketing_bps = 0;
+        assert_eq!(escrow.split_fee(1), (0, 1));
+    }
+
+    #[test]
+    fn second_initialize_finds_the_vault_funded() {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = [];
+        let vault = AccountInfo::new(
+            &key,
+            false,
+            true,
+            &mut lamports,
+            &mut data,
+            &system_program::ID,
+            false,
+            0,
+        );
+        assert_eq!(require_fresh_vault(&vault), Ok(()));
+
+        // the first `initialize` left it rent-funded
+        **vault.try_borrow_mut_lamports().unwrap() = 890_880;
+        assert_eq!(
+            require_fresh_vault(&vault),
+            Err(ErrorCode::AlreadyInitialized.into())
+        );
+    }
+
+    #[test]
+    fn marketing_bps_frozen_while_timer_runs() {
+        let mut escrow = escrow();
+        escrow.marketing_bps = 500;
+        assert_eq!(escrow.require_marketing_bps_change(800), Ok(()));
+
+        escrow.timer_active = true;
+        assert_eq!(
+            escrow.require_marketing_bps_change(800),
+            Err(ErrorCode::ParamsLockedDuringEndgame.into())
+        );
+        assert_eq!(
+            escrow.require_marketing_bps_change(300),
+            Err(ErrorCode::ParamsLockedDuringEndgame.into())
+        );
+        // the wallet can still be rotated at the same bps
+        assert_eq!(escrow.require_marketing_bps_change(500), Ok(()));
+
+        escrow.ended = true;
+        assert_eq!(escrow.require_marketing_bps_change(800), Ok(()));
+
+        escrow.ended = false;
+        escrow.timer_active = false;
+        assert_eq!(escrow.require_marketing_bps_change(800), Ok(()));
+    }
+
+    #[test]
+    fn base_fee_raise_refused_in_endgame() {
+        let mut escrow = escrow();
+        escrow.base_fee = 100;
+        assert_eq!(escrow.require_base_fee_change(500), Ok(()));
+
+        escrow.timer_active = true;
+        assert_eq!(
+            escrow.require_base_fee_change(101),
+            Err(ErrorCode::BaseFeeLockedDuringGame.into())
+        );
+        assert_eq!(escrow.require_base_fee_change(100), Ok(()));
+        assert_eq!(escrow.require_base_fee_change(50), Ok(()));
+
+        escrow.ended = true;
+        assert_eq!(escrow.require_base_fee_change(500), Ok(()));
+    }
+
+    #[test]
+    fn current_fee_raise_refused_in_endgame() {
+        let mut escrow = escrow();
+        escrow.current_fee = 100;
+        assert_eq!(escrow.require_current_fee_change(500), Ok(()));
+
+        escrow.timer_active = true;
+        assert_eq!(
+            escrow.require_current_fee_change(101),
+            Err(ErrorCode::CurrentFeeLockedDuringGame.into())
+        );
+        assert_eq!(escrow.require_current_fee_change(100), Ok(()));
+        assert_eq!(escrow.require_current_fee_change(50), Ok(()));
+
+        escrow.ended = true;
+        assert_eq!(escrow.require_current_fee_change(500), Ok(()));
+    }
+
+    #[test]
+    fn quick_second_param_change_is_refused() {
+        let mut escrow = escrow();
+        escrow.param_change_cooldown = 60;
+        assert_eq!(escrow.note_param_change(1_000), Ok(()));
+        assert_eq!(
+            escrow.note_param_change(1_010),
+            Err(ErrorCode::ParamChangeTooSoon.into())
+        );
+        // the refused attempt doesn't restart the clock
+        assert_eq!(escrow.last_param_change_ts, 1_000);
+        assert_eq!(escrow.note_param_change(1_060), Ok(()));
+
+        escrow.param_change_cooldown = 0;
+        assert_eq!(escrow.note_param_change(1_060), Ok(()));
+    }
+
+    #[test]
+    fn self_deal_collisions() {
+        let mut escrow = escrow();
+        escrow.authority = Pubkey::new_unique();
+        escrow.marketing_wallet = Pubkey::new_unique();
+        escrow.set_single_marketing_recipient(escrow.marketing_wallet, 500);
+        let extra = Pubkey::new_unique();
+        escrow.marketing_splits[1] = MarketingSplit {
+            wallet: extra,
+            bps: 100,
+        };
+        let player = Pubkey::new_unique();
+
+        // off by default
+        assert_eq!(escrow.require_no_self_deal(escrow.authority), Ok(()));
+
+        escrow.prevent_self_deal = true;
+        for winner in [escrow.authority, escrow.marketing_wallet, extra] {
+            assert_eq!(
+                escrow.require_no_self_deal(winner),
+                Err(ErrorCode::SelfDealBlocked.into())
+            );
+        }
+        assert_eq!(escrow.require_no_self_deal(player), Ok(()));
+
+        // `claim_status` (and so `claim_prize` and `can_i_claim`) refuses the
+        // same winners
+        escrow.timer_active = true;
+        escrow.deadline = 1_000;
+        escrow.unique_participants = 1;
+        escrow.min_unique_to_claim = 1;
+        for winner in [escrow.authority, escrow.marketing_wallet, extra] {
+            escrow.last_sender = winner;
+            assert_eq!(
+                escrow.claim_status(&config(), winner, 1_000),
+                claim_reason::SELF_DEAL_BLOCKED
+            );
+        }
+        escrow.last_sender = player;
+        assert_eq!(escrow.claim_status(&config(), player, 1_000), claim_reason::ELIGIBLE);
+    }
+
+    #[test]
+    fn authority_as_marketing_wallet_under_distinct_policy() {
+        let mut config = config();
+        let authority = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+
+        // off by default
+        assert_eq!(config.require_distinct_marketing_wallet(&authority, &authority), Ok(()));
+
+        config.require_distinct_marketing = true;
+        assert_eq!(
+            config.require_distinct_marketing_wallet(&authority, &authority),
+            Err(ErrorCode::InvalidMarketingWallet.into())
+        );
+        assert_eq!(config.require_distinct_marketing_wallet(&authority, &other), Ok(()));
+    }
+
+    #[test]
+    fn latched_deadline_refuses_submissions() {
+        let config = config();
+        let mut escrow = expired(Pubkey::new_unique());
+        escrow.max_participants = u64::MAX;
+        escrow.max_submissions_per_slot = 1;
+        let attempt = SubmitAttempt {
+            participant: None,
+            client_nonce: None,
+            vault_lamports: 1,
+            deposit: false,
+            now: 999,
+            slot: 0,
+        };
+        assert_eq!(submission_status(&config, &escrow, &attempt), submit_reason::ELIGIBLE);
+
+        escrow.deadline_reached = true;
+        assert_eq!(submission_status(&config, &escrow, &attempt), submit_reason::TIMER_EXPIRED);
+    }
+
+    #[test]
+    fn new_wallet_counts_toward_its_own_extension() {
+        let mut escrow = escrow();
+        escrow.unique_participants = 1;
+        escrow.min_unique_for_extension = 2;
+        assert!(escrow.extension_gated());
+
+        // the second wallet's first submission is the one that reaches the
+        // threshold, and must extend the timer
+        let mut participant = Participant::deserialize(&mut &[0u8; Participant::LEN][..]).unwrap();
+        join_game(&mut escrow, &mut participant, Pubkey::new_unique(), 255, 500);
+        assert_eq!(escrow.unique_participants, 2);
+        assert!(!escrow.extension_gated());
+
+        // a later `track_participant` for the same submission doesn't count it twice
+        let wallet = participant.wallet;
+        track_participant(&mut escrow, &mut participant, wallet, 255, 1, 10, 500);
+        assert_eq!(escrow.unique_participants, 2);
+        assert_eq!(participant.messages_count, 1);
+    }
+
+    #[test]
+    fn snipe_refund_comes_from_the_prize_leg() {
+        let mut escrow = escrow();
+        escrow.sniped_refund_bps = 5_000;
+        escrow.last_sender = Pubkey::new_unique();
+        // a 1_000 fee of which 200 went to marketing
+        escrow.recent_fees[0] = 1_000;
+        escrow.recent_fees_head = 1;
+        escrow.last_prize_fee = 800;
+
+        escrow.note_snipe(11, 10);
+        assert_eq!(escrow.sniped_wallet, escrow.last_sender);
+        assert_eq!(escrow.sniped_refund, 400);
+
+        // outbid two slots later isn't a snipe
+        escrow.sniped_refund = 0;
+        escrow.note_snipe(12, 10);
+        assert_eq!(escrow.sniped_refund, 0);
+    }
+}